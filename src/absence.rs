@@ -0,0 +1,320 @@
+use crate::{schedule::WorkSchedule, source::Query};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::{collections::BTreeMap, io, path::PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AbsenceError {
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+    #[error("CSV reader error")]
+    Csv(#[from] csv::Error),
+    #[error("Unknown absence kind '{0}'")]
+    UnknownKind(String),
+    #[error("Absence ends before it starts on {0}")]
+    InvalidPeriod(NaiveDate),
+}
+
+/// Period of absence as recorded in an absences file or time tracker, e.g. a
+/// vacation from `start` until `end` (inclusive).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AbsencePeriod {
+    /// Kind of absence like `vacation` or `sick`, or the name of the time off
+    /// policy of the time tracker.
+    pub kind: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub half_day: bool,
+}
+
+/// How an absence day counts toward the target working time.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AbsenceCredit {
+    /// The target of the day is fulfilled, e.g. for vacation or sick leave.
+    /// Half days fulfil half of the target.
+    #[default]
+    Target,
+    /// The target of the day remains, so the absence reduces the flextime
+    /// balance, e.g. for days off compensating overtime.
+    None,
+}
+
+/// Rule for a kind of absence, configured in the `[absences.kinds]` table of
+/// the config file.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct AbsenceRule {
+    /// Description of the marker rows of absence days in the time sheet.
+    pub label: String,
+    #[serde(default)]
+    pub credit: AbsenceCredit,
+    /// Names of time off policies of the time tracker of this kind.
+    #[serde(default)]
+    pub policies: Vec<String>,
+}
+
+/// Sources of absences and rules for their kinds, configured in the
+/// `[absences]` table of the config file.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AbsenceConfig {
+    /// CSV file listing absences (see `read_absences`).
+    pub file: Option<PathBuf>,
+    /// Retrieve the approved time off requests from Clockify.
+    pub clockify: bool,
+    /// Rules by kind, added to or replacing the built-in kinds `vacation`
+    /// ("Urlaub") and `sick` ("Krank").
+    pub kinds: BTreeMap<String, AbsenceRule>,
+}
+
+impl AbsenceConfig {
+    /// Rule for the kind or time off policy, matched case-insensitively
+    /// against the configured kinds first and the built-in kinds second.
+    pub fn rule(&self, kind: &str) -> Option<AbsenceRule> {
+        let defaults = default_kinds();
+        let defaults = defaults
+            .iter()
+            .filter(|(name, _)| !self.kinds.contains_key(name));
+        self.kinds
+            .iter()
+            .chain(defaults.map(|(name, rule)| (name, rule)))
+            .find(|(name, rule)| {
+                name.eq_ignore_ascii_case(kind)
+                    || rule
+                        .policies
+                        .iter()
+                        .any(|policy| policy.eq_ignore_ascii_case(kind))
+            })
+            .map(|(_, rule)| rule.clone())
+    }
+
+    /// Resolve the periods into the absence days of the query. Only working
+    /// days of the schedule, which are no holidays, are absence days.
+    /// Periods of the absences file must be of a known kind, while time off
+    /// policies without rule are labeled by their name and fulfil the target.
+    pub fn resolve(
+        &self,
+        periods: &[AbsencePeriod],
+        schedule: &WorkSchedule,
+        query: &Query,
+    ) -> Result<Vec<Absence>, AbsenceError> {
+        let mut absences: BTreeMap<NaiveDate, Absence> = BTreeMap::new();
+        for period in periods {
+            if period.end < period.start {
+                return Err(AbsenceError::InvalidPeriod(period.start));
+            }
+            let rule = self.rule(&period.kind).unwrap_or_else(|| AbsenceRule {
+                label: period.kind.clone(),
+                credit: AbsenceCredit::Target,
+                policies: vec![],
+            });
+            for date in period
+                .start
+                .iter_days()
+                .take_while(|date| *date <= period.end)
+            {
+                if date < query.start || date >= query.end || !schedule.is_working_day(date) {
+                    continue;
+                }
+                absences.insert(
+                    date,
+                    Absence {
+                        date,
+                        label: rule.label.clone(),
+                        credit: rule.credit,
+                        half_day: period.half_day,
+                    },
+                );
+            }
+        }
+        Ok(absences.into_values().collect())
+    }
+
+    /// Check that all periods of the absences file are of a known kind.
+    pub fn check(&self, periods: &[AbsencePeriod]) -> Result<(), AbsenceError> {
+        match periods
+            .iter()
+            .find(|period| self.rule(&period.kind).is_none())
+        {
+            Some(period) => Err(AbsenceError::UnknownKind(period.kind.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Built-in kinds of absences.
+fn default_kinds() -> [(String, AbsenceRule); 2] {
+    [
+        (
+            "sick".to_string(),
+            AbsenceRule {
+                label: "Krank".to_string(),
+                credit: AbsenceCredit::Target,
+                policies: vec!["Sick leave".to_string(), "Krank".to_string()],
+            },
+        ),
+        (
+            "vacation".to_string(),
+            AbsenceRule {
+                label: "Urlaub".to_string(),
+                credit: AbsenceCredit::Target,
+                policies: vec!["Urlaub".to_string()],
+            },
+        ),
+    ]
+}
+
+/// Absence on a single day.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Absence {
+    pub date: NaiveDate,
+    /// Description of the marker row, e.g. `Urlaub`.
+    pub label: String,
+    pub credit: AbsenceCredit,
+    pub half_day: bool,
+}
+
+/// Row of an absences file.
+#[derive(Debug, Deserialize)]
+struct AbsenceRecord {
+    start: NaiveDate,
+    end: Option<NaiveDate>,
+    kind: String,
+    half_day: Option<String>,
+}
+
+/// Read absences from a CSV file with the columns `start`, `end` (inclusive,
+/// empty for a single day), `kind` and optionally `half_day` (`yes` or `no`).
+/// Dates are given as `YYYY-MM-DD`.
+pub fn read_absences<R: io::Read>(rdr: R) -> Result<Vec<AbsencePeriod>, AbsenceError> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(rdr);
+    rdr.deserialize()
+        .map(|record| {
+            let record: AbsenceRecord = record?;
+            Ok(AbsencePeriod {
+                kind: record.kind,
+                start: record.start,
+                end: record.end.unwrap_or(record.start),
+                half_day: record
+                    .half_day
+                    .is_some_and(|half_day| half_day.eq_ignore_ascii_case("yes")),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2022, 10, day).unwrap()
+    }
+
+    #[test]
+    fn test_read_absences() {
+        let absences = read_absences(
+            "start,end,kind,half_day
+2022-10-10,2022-10-14,vacation,
+2022-10-20,,sick,yes
+"
+            .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            absences,
+            vec![
+                AbsencePeriod {
+                    kind: "vacation".to_string(),
+                    start: date(10),
+                    end: date(14),
+                    half_day: false,
+                },
+                AbsencePeriod {
+                    kind: "sick".to_string(),
+                    start: date(20),
+                    end: date(20),
+                    half_day: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_absence_rules() {
+        let config: AbsenceConfig = toml::from_str(
+            r#"
+            [kinds.vacation]
+            label = "Urlaubstag"
+            policies = ["Annual leave"]
+            [kinds.overtime]
+            label = "Gleitzeit"
+            credit = "none"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.rule("Vacation").unwrap().label, "Urlaubstag");
+        assert_eq!(config.rule("Annual leave").unwrap().label, "Urlaubstag");
+        assert_eq!(config.rule("Urlaub"), None);
+        assert_eq!(config.rule("sick leave").unwrap().label, "Krank");
+        assert_eq!(config.rule("overtime").unwrap().credit, AbsenceCredit::None);
+        assert!(config
+            .check(&[AbsencePeriod {
+                kind: "parental leave".to_string(),
+                start: date(3),
+                end: date(3),
+                half_day: false,
+            }])
+            .is_err());
+    }
+
+    #[test]
+    fn test_resolve_absences_on_working_days() {
+        let config = AbsenceConfig::default();
+        let periods = vec![
+            // Friday, 2022-09-30, until Tuesday, 2022-10-04.
+            AbsencePeriod {
+                kind: "vacation".to_string(),
+                start: NaiveDate::from_ymd_opt(2022, 9, 30).unwrap(),
+                end: date(4),
+                half_day: false,
+            },
+            AbsencePeriod {
+                kind: "Elternzeit".to_string(),
+                start: date(6),
+                end: date(6),
+                half_day: true,
+            },
+        ];
+        let schedule: WorkSchedule = toml::from_str(
+            r#"
+            [holidays]
+            region = "DE"
+            "#,
+        )
+        .unwrap();
+        let absences = config
+            .resolve(&periods, &schedule, &Query::month(2022, 10).unwrap())
+            .unwrap();
+        assert_eq!(
+            absences,
+            vec![
+                Absence {
+                    date: date(4),
+                    label: "Urlaub".to_string(),
+                    credit: AbsenceCredit::Target,
+                    half_day: false,
+                },
+                Absence {
+                    date: date(6),
+                    label: "Elternzeit".to_string(),
+                    credit: AbsenceCredit::Target,
+                    half_day: true,
+                },
+            ]
+        );
+    }
+}
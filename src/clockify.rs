@@ -1,10 +1,16 @@
-use chrono::{DateTime, Local};
+use crate::{
+    absence::AbsencePeriod,
+    source::{Entry, Query, SourceError, TimeSource},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate};
 use reqwest::header::{self, HeaderValue};
 use serde::Deserialize;
 use std::collections::HashMap;
 use thiserror::Error;
 
 static CLOCKIFY_API_BASE: &str = "https://api.clockify.me/api/v1";
+static CLOCKIFY_PTO_API_BASE: &str = "https://pto.api.clockify.me/v1";
 
 #[derive(Debug, Error)]
 pub enum ClockifyError {
@@ -29,6 +35,15 @@ pub struct ApiUser {
     user: User,
 }
 
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub client_name: Option<String>,
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Task {
@@ -36,6 +51,13 @@ pub struct Task {
     pub name: String,
 }
 
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct TimeInterval {
@@ -49,8 +71,64 @@ pub struct TimeEntry {
     pub description: String,
     pub billable: bool,
     pub task_id: Option<String>,
+    #[serde(default)]
+    pub tag_ids: Option<Vec<String>>,
     pub time_interval: TimeInterval,
     pub task: Option<Task>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeOffRequest {
+    pub policy_name: String,
+    pub time_off_period: TimeOffPeriod,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeOffPeriod {
+    pub period: Period,
+    #[serde(default)]
+    pub half_day: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Period {
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct TimeOffRequests {
+    requests: Vec<TimeOffRequest>,
+}
+
+/// Time source retrieving the time entries of a project via the Clockify API.
+#[derive(Clone, Debug)]
+pub struct ClockifySource {
+    pub api_key: String,
+    pub project_id: String,
+}
+
+#[async_trait]
+impl TimeSource for ClockifySource {
+    async fn retrieve(&self, query: &Query) -> Result<Vec<Entry>, SourceError> {
+        let api_user = get_api_user(&self.api_key).await?;
+        let project = get_project(&api_user, &self.project_id).await?;
+        let time_entries = retrieve_time_entries(&api_user, &self.project_id, query).await?;
+        let mut entries = time_entries
+            .into_iter()
+            .map(|entry| into_entry(entry, &project))
+            .filter(|entry| query.matches(entry))
+            .collect::<Vec<_>>();
+        // Clockify starts with the newest entry.
+        entries.sort_by_key(|entry| entry.start);
+        Ok(entries)
+    }
 }
 
 /// Retrieve user ID and active workspace ID from Clockify API.
@@ -69,12 +147,26 @@ pub async fn get_api_user(api_key: &str) -> Result<ApiUser, ClockifyError> {
     })
 }
 
-/// Retrieve time entries for the given project from Clockify.
+/// Retrieve the project with the given ID from Clockify.
+pub async fn get_project(api_user: &ApiUser, project_id: &str) -> Result<Project, ClockifyError> {
+    let client = build_client(&api_user.api_key)?;
+
+    let response = client
+        .get(format!(
+            "{}/workspaces/{}/projects/{}",
+            CLOCKIFY_API_BASE, api_user.user.active_workspace, project_id
+        ))
+        .send()
+        .await?;
+    let response_body = response.text().await?;
+    Ok(serde_json::from_str(&response_body)?)
+}
+
+/// Retrieve time entries for the given project and query from Clockify.
 pub async fn retrieve_time_entries(
     api_user: &ApiUser,
     project_id: &str,
-    year: u32,
-    month: u32,
+    query: &Query,
 ) -> Result<Vec<TimeEntry>, ClockifyError> {
     let client = build_client(&api_user.api_key)?;
 
@@ -89,9 +181,20 @@ pub async fn retrieve_time_entries(
     let response_body = response.text().await?;
     let tasks: Vec<Task> = serde_json::from_str(&response_body)?;
 
+    // Get tags from Clockify.
+    let response = client
+        .get(format!(
+            "{}/workspaces/{}/tags",
+            CLOCKIFY_API_BASE, api_user.user.active_workspace
+        ))
+        .send()
+        .await?;
+    let response_body = response.text().await?;
+    let tags: Vec<Tag> = serde_json::from_str(&response_body)?;
+
     // Get time entries from Clockify.
     let mut time_entries: Vec<TimeEntry> = vec![];
-    let start_end_range = StartEndRange::from_year_and_month(year, month);
+    let start_end_range = StartEndRange::from_query(query);
     let (start, end) = (start_end_range.start(), start_end_range.end());
     // The API delivers 50 entries per page. Limiting retrieval to 5 page
     // requests in case something goes wrong, results in a maximum of 250
@@ -99,6 +202,7 @@ pub async fn retrieve_time_entries(
     // TODO: The maximum number of pages should be configurable. Note that at
     //       some point, the API limit of 10 requests per second will kick in
     //       and will have to be handled.
+    let filter = time_entry_filter(query, &tags);
     for page in 1..=5 {
         let response = client
             .get(format!(
@@ -111,6 +215,7 @@ pub async fn retrieve_time_entries(
                 end,
                 page
             ))
+            .query(&filter)
             .send()
             .await?;
         let response_body = response.text().await?;
@@ -121,7 +226,56 @@ pub async fn retrieve_time_entries(
         time_entries.extend(entries);
     }
 
-    Ok(resolve_task_ids(time_entries, tasks))
+    Ok(resolve_tag_ids(resolve_task_ids(time_entries, tasks), tags))
+}
+
+/// Query parameters filtering the time entries by the tags of the query, given
+/// by the IDs of the tags known in the workspace. The time entries endpoint
+/// has no billable filter, so like unknown tags, the billable flag is only
+/// filtered after retrieval (see `Query::matches`).
+fn time_entry_filter(query: &Query, tags: &[Tag]) -> Vec<(&'static str, String)> {
+    tags.iter()
+        .filter(|tag| query.tags.contains(&tag.name))
+        .map(|tag| ("tags", tag.id.clone()))
+        .collect()
+}
+
+/// Retrieve the approved time off requests of the user overlapping the query
+/// from Clockify.
+pub async fn retrieve_time_off(
+    api_user: &ApiUser,
+    query: &Query,
+) -> Result<Vec<AbsencePeriod>, ClockifyError> {
+    let client = build_client(&api_user.api_key)?;
+
+    let mut requests: Vec<TimeOffRequest> = vec![];
+    let start_end_range = StartEndRange::from_query(query);
+    // Like time entries, limit retrieval to 5 pages of 50 requests.
+    for page in 1..=5 {
+        let response = client
+            .post(format!(
+                "{}/workspaces/{}/requests",
+                CLOCKIFY_PTO_API_BASE, api_user.user.active_workspace
+            ))
+            .json(&serde_json::json!({
+                "start": start_end_range.start(),
+                "end": start_end_range.end(),
+                "statuses": ["APPROVED"],
+                "users": [api_user.user.id],
+                "page": page,
+                "pageSize": 50,
+            }))
+            .send()
+            .await?;
+        let response_body = response.text().await?;
+        let page_requests = serde_json::from_str::<TimeOffRequests>(&response_body)?.requests;
+        if page_requests.is_empty() {
+            break;
+        }
+        requests.extend(page_requests);
+    }
+
+    Ok(requests.into_iter().map(into_absence_period).collect())
 }
 
 /// Build a reqwest client for accessing the API.
@@ -136,26 +290,24 @@ fn build_client(api_key: &str) -> Result<reqwest::Client, ClockifyError> {
 }
 
 /// Struct for providing the start and end filter values for limiting the time
-/// entries query to the given year and month.
+/// entries query to the queried days.
 struct StartEndRange {
-    year: u32,
-    month: u32,
+    start: NaiveDate,
+    end: NaiveDate,
 }
 
 impl StartEndRange {
-    fn from_year_and_month(year: u32, month: u32) -> Self {
-        Self { year, month }
+    fn from_query(query: &Query) -> Self {
+        Self {
+            start: query.start,
+            end: query.end,
+        }
     }
     fn start(&self) -> String {
-        format!("{}-{:02}-01T00:00:00Z", self.year, self.month)
+        format!("{}T00:00:00Z", self.start.format("%Y-%m-%d"))
     }
     fn end(&self) -> String {
-        let (year, month) = if self.month == 12 {
-            (self.year + 1, 1)
-        } else {
-            (self.year, self.month + 1)
-        };
-        format!("{year}-{month:02}-01T00:00:00Z")
+        format!("{}T00:00:00Z", self.end.format("%Y-%m-%d"))
     }
 }
 
@@ -179,25 +331,81 @@ fn resolve_task_ids(time_entries: Vec<TimeEntry>, tasks: Vec<Task>) -> Vec<TimeE
         })
         .collect()
 }
+
+/// Resolve tag IDs in time entries to corresponding tags and populate `tags`
+/// fields with tag data. Unknown tag IDs are skipped.
+fn resolve_tag_ids(time_entries: Vec<TimeEntry>, tags: Vec<Tag>) -> Vec<TimeEntry> {
+    // Convert tag list into a hash map for faster lookup.
+    let tags_map = tags
+        .into_iter()
+        .map(|tag| (tag.id.clone(), tag))
+        .collect::<HashMap<_, _>>();
+
+    // Clone corresponding tags into `tags` fields of time entries.
+    time_entries
+        .into_iter()
+        .map(|mut entry| {
+            if let Some(ref tag_ids) = entry.tag_ids {
+                entry.tags = tag_ids
+                    .iter()
+                    .filter_map(|tag_id| tags_map.get(tag_id).cloned())
+                    .collect();
+            }
+            entry
+        })
+        .collect()
+}
+
+/// Convert a Clockify time entry of the given project into a normalized entry.
+fn into_entry(time_entry: TimeEntry, project: &Project) -> Entry {
+    Entry {
+        description: time_entry.description,
+        project: Some(project.name.clone()),
+        client: project.client_name.clone().filter(|name| !name.is_empty()),
+        task: time_entry.task.map(|task| task.name),
+        tags: time_entry.tags.into_iter().map(|tag| tag.name).collect(),
+        billable: time_entry.billable,
+        start: time_entry.time_interval.start,
+        end: time_entry.time_interval.end,
+    }
+}
+
+/// Convert a Clockify time off request into a period of absence of the kind
+/// of its policy. The end of the period is exclusive, if it is midnight.
+fn into_absence_period(request: TimeOffRequest) -> AbsencePeriod {
+    let period = request.time_off_period.period;
+    let start = period.start.date_naive();
+    AbsencePeriod {
+        kind: request.policy_name,
+        start,
+        end: (period.end - Duration::seconds(1)).date_naive().max(start),
+        half_day: request.time_off_period.half_day,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn month_range(year: u32, month: u32) -> StartEndRange {
+        StartEndRange::from_query(&Query::month(year, month).unwrap())
+    }
+
     #[test]
     fn test_start_end_range_start() {
-        let range = StartEndRange::from_year_and_month(2022, 9);
+        let range = month_range(2022, 9);
         assert_eq!(range.start(), "2022-09-01T00:00:00Z");
-        let range = StartEndRange::from_year_and_month(1999, 12);
+        let range = month_range(1999, 12);
         assert_eq!(range.start(), "1999-12-01T00:00:00Z");
     }
 
     #[test]
     fn test_start_end_range_end() {
-        let range = StartEndRange::from_year_and_month(1999, 9);
+        let range = month_range(1999, 9);
         assert_eq!(range.end(), "1999-10-01T00:00:00Z");
-        let range = StartEndRange::from_year_and_month(2022, 11);
+        let range = month_range(2022, 11);
         assert_eq!(range.end(), "2022-12-01T00:00:00Z");
-        let range = StartEndRange::from_year_and_month(2022, 12);
+        let range = month_range(2022, 12);
         assert_eq!(range.end(), "2023-01-01T00:00:00Z");
     }
 
@@ -218,21 +426,25 @@ mod tests {
                 description: "Entry 1".to_string(),
                 billable: true,
                 task_id: Some("abcdef".to_string()),
+                tag_ids: None,
                 time_interval: TimeInterval {
                     start: Local::now(),
                     end: Local::now(),
                 },
                 task: None,
+                tags: vec![],
             },
             TimeEntry {
                 description: "Entry 2".to_string(),
                 billable: true,
                 task_id: Some("ghijkl".to_string()),
+                tag_ids: None,
                 time_interval: TimeInterval {
                     start: Local::now(),
                     end: Local::now(),
                 },
                 task: None,
+                tags: vec![],
             },
         ];
         let mut expected_result = time_entries.clone();
@@ -253,25 +465,198 @@ mod tests {
                 description: "Entry 1".to_string(),
                 billable: true,
                 task_id: Some("abcdef".to_string()),
+                tag_ids: None,
                 time_interval: TimeInterval {
                     start: Local::now(),
                     end: Local::now(),
                 },
                 task: None,
+                tags: vec![],
             },
             TimeEntry {
                 description: "Entry 2".to_string(),
                 billable: true,
                 task_id: None,
+                tag_ids: None,
                 time_interval: TimeInterval {
                     start: Local::now(),
                     end: Local::now(),
                 },
                 task: None,
+                tags: vec![],
             },
         ];
         let expected_result = time_entries.clone();
         let result = resolve_task_ids(time_entries, tasks);
         assert_eq!(result, expected_result);
     }
+
+    #[test]
+    fn test_resolve_tag_ids() {
+        let tags = vec![
+            Tag {
+                id: "tag1".to_string(),
+                name: "meeting".to_string(),
+            },
+            Tag {
+                id: "tag2".to_string(),
+                name: "travel".to_string(),
+            },
+        ];
+        let time_entries = vec![
+            TimeEntry {
+                description: "Entry 1".to_string(),
+                billable: true,
+                task_id: None,
+                tag_ids: Some(vec![
+                    "tag2".to_string(),
+                    "unknown".to_string(),
+                    "tag1".to_string(),
+                ]),
+                time_interval: TimeInterval {
+                    start: Local::now(),
+                    end: Local::now(),
+                },
+                task: None,
+                tags: vec![],
+            },
+            TimeEntry {
+                description: "Entry 2".to_string(),
+                billable: true,
+                task_id: None,
+                tag_ids: None,
+                time_interval: TimeInterval {
+                    start: Local::now(),
+                    end: Local::now(),
+                },
+                task: None,
+                tags: vec![],
+            },
+        ];
+        let mut expected_result = time_entries.clone();
+        expected_result[0].tags = vec![tags[1].clone(), tags[0].clone()];
+        let result = resolve_tag_ids(time_entries, tags);
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn test_time_entry_filter() {
+        let tags = vec![
+            Tag {
+                id: "tag1".to_string(),
+                name: "meeting".to_string(),
+            },
+            Tag {
+                id: "tag2".to_string(),
+                name: "travel".to_string(),
+            },
+        ];
+        let query = Query::month(2022, 10).unwrap();
+        assert!(time_entry_filter(&query, &tags).is_empty());
+        let query = Query {
+            billable: Some(true),
+            tags: vec!["travel".to_string(), "unknown".to_string()],
+            ..query
+        };
+        assert_eq!(
+            time_entry_filter(&query, &tags),
+            vec![("tags", "tag2".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_into_entry() {
+        let project = Project {
+            id: "project".to_string(),
+            name: "Project".to_string(),
+            client_name: Some("Client".to_string()),
+        };
+        let time_entry = TimeEntry {
+            description: "Entry 1".to_string(),
+            billable: false,
+            task_id: Some("abcdef".to_string()),
+            tag_ids: Some(vec!["tag1".to_string()]),
+            time_interval: TimeInterval {
+                start: Local::now(),
+                end: Local::now(),
+            },
+            task: Some(Task {
+                id: "abcdef".to_string(),
+                name: "Task 1".to_string(),
+            }),
+            tags: vec![Tag {
+                id: "tag1".to_string(),
+                name: "meeting".to_string(),
+            }],
+        };
+        let expected_result = Entry {
+            description: "Entry 1".to_string(),
+            project: Some("Project".to_string()),
+            client: Some("Client".to_string()),
+            task: Some("Task 1".to_string()),
+            tags: vec!["meeting".to_string()],
+            billable: false,
+            start: time_entry.time_interval.start,
+            end: time_entry.time_interval.end,
+        };
+        let result = into_entry(time_entry, &project);
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn test_into_absence_period() {
+        let requests: TimeOffRequests = serde_json::from_str(
+            r#"{
+                "count": 2,
+                "requests": [
+                    {
+                        "id": "request1",
+                        "policyName": "Vacation",
+                        "timeOffPeriod": {
+                            "period": {
+                                "start": "2022-10-10T00:00:00Z",
+                                "end": "2022-10-15T00:00:00Z"
+                            },
+                            "halfDay": false
+                        }
+                    },
+                    {
+                        "id": "request2",
+                        "policyName": "Sick leave",
+                        "timeOffPeriod": {
+                            "period": {
+                                "start": "2022-10-20T08:00:00+02:00",
+                                "end": "2022-10-20T12:00:00+02:00"
+                            },
+                            "halfDay": true
+                        }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let date = |day| NaiveDate::from_ymd_opt(2022, 10, day).unwrap();
+        let result = requests
+            .requests
+            .into_iter()
+            .map(into_absence_period)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            vec![
+                AbsencePeriod {
+                    kind: "Vacation".to_string(),
+                    start: date(10),
+                    end: date(14),
+                    half_day: false,
+                },
+                AbsencePeriod {
+                    kind: "Sick leave".to_string(),
+                    start: date(20),
+                    end: date(20),
+                    half_day: true,
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,496 @@
+use crate::{holidays::Holidays, transform::TimeSheetEntry};
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Weekday};
+use std::{fmt, ops::Range};
+
+/// Maximum working time per day in hours (§ 3 ArbZG).
+const MAX_WORKING_TIME: i64 = 10;
+/// Minimum rest period between working days in hours (§ 5 ArbZG).
+const MIN_REST_PERIOD: i64 = 11;
+/// Minimum gap in minutes between entries counting as break (§ 4 ArbZG).
+const MIN_BREAK_SEGMENT: i64 = 15;
+
+/// Rule of the German working time law (Arbeitszeitgesetz) violated on a day.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ViolationKind {
+    /// More than 10 hours of working time.
+    ExcessiveWorkingTime {
+        working_time: Duration,
+    },
+    /// Less than 30 minutes of breaks after more than 6 hours, or less than 45
+    /// minutes after more than 9 hours of working time.
+    InsufficientBreak {
+        working_time: Duration,
+        break_: Duration,
+        required: Duration,
+    },
+    /// Less than 11 hours of rest since the end of work on the previous day.
+    InsufficientRest {
+        rest: Duration,
+    },
+    SundayWork,
+    HolidayWork {
+        holiday: String,
+    },
+}
+
+/// Violation of the working time law on a day.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Violation {
+    pub date: NaiveDate,
+    pub kind: ViolationKind,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.date.format("%d.%m.%y"))?;
+        match &self.kind {
+            ViolationKind::ExcessiveWorkingTime { working_time } => write!(
+                f,
+                "Working time of {} exceeds {MAX_WORKING_TIME} hours",
+                format_duration(working_time)
+            ),
+            ViolationKind::InsufficientBreak {
+                working_time,
+                break_,
+                required,
+            } => write!(
+                f,
+                "Break of {} is shorter than the required {} for {} working time",
+                format_duration(break_),
+                format_duration(required),
+                format_duration(working_time)
+            ),
+            ViolationKind::InsufficientRest { rest } => write!(
+                f,
+                "Rest period of {} since the previous day is shorter than {MIN_REST_PERIOD} hours",
+                format_duration(rest)
+            ),
+            ViolationKind::SundayWork => write!(f, "Work on Sunday"),
+            ViolationKind::HolidayWork { holiday } => write!(f, "Work on holiday ({holiday})"),
+        }
+    }
+}
+
+/// Break added to a time sheet entry to meet the required break of its day.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BreakAdjustment {
+    pub date: NaiveDate,
+    /// Description of the entry the break was added to or which was shortened
+    /// to extend the gap after or before it.
+    pub description: String,
+    pub added: Duration,
+    /// Working time of the day after adding the break.
+    pub working_time: Duration,
+}
+
+impl fmt::Display for BreakAdjustment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: Added break of {} to \"{}\" as required for {} working time",
+            self.date.format("%d.%m.%y"),
+            format_duration(&self.added),
+            self.description,
+            format_duration(&self.working_time)
+        )
+    }
+}
+
+/// Check the time sheet entries for violations of the German working time law
+/// (Arbeitszeitgesetz), ordered by date. Entries count towards the day they
+/// start on. Breaks of a day are the breaks of its entries plus the gaps
+/// between them, each counting if it lasts at least 15 minutes.
+pub fn check_working_time_law(
+    time_sheet_entries: &[TimeSheetEntry],
+    holidays: &Holidays,
+) -> Vec<Violation> {
+    let mut violations = vec![];
+    let mut previous_end = None;
+    for (date, range) in days(time_sheet_entries) {
+        let entries = &time_sheet_entries[range];
+        let violation = |kind| Violation { date, kind };
+        let working_time = working_time(entries);
+        let break_ = break_time(entries);
+        let first_start = entries.iter().map(|entry| entry.start).min();
+        let last_end = entries.iter().map(|entry| entry.end).max();
+
+        if working_time > Duration::hours(MAX_WORKING_TIME) {
+            violations.push(violation(ViolationKind::ExcessiveWorkingTime {
+                working_time,
+            }));
+        }
+        let required = required_break(working_time);
+        if break_ < required {
+            violations.push(violation(ViolationKind::InsufficientBreak {
+                working_time,
+                break_,
+                required,
+            }));
+        }
+        if let (Some(previous_end), Some(first_start)) = (previous_end, first_start) {
+            let rest = first_start - previous_end;
+            if rest < Duration::hours(MIN_REST_PERIOD) {
+                violations.push(violation(ViolationKind::InsufficientRest { rest }));
+            }
+        }
+        if date.weekday() == Weekday::Sun {
+            violations.push(violation(ViolationKind::SundayWork));
+        }
+        if let Some(holiday) = holidays.holiday(date) {
+            violations.push(violation(ViolationKind::HolidayWork { holiday }));
+        }
+        previous_end = last_end;
+    }
+    violations
+}
+
+/// Add the break missing for the required break of each day, which reduces
+/// the working time accordingly. The whole shortfall is added, but at least
+/// 15 minutes, as shorter breaks do not count (§ 4 ArbZG). The largest gap
+/// between the entries of the day is extended by ending the entry before it
+/// earlier (or starting the entry after it later), as it most likely is the
+/// actual break. Without gaps, the break is added to the entry spanning noon,
+/// otherwise to the longest entry. Returns the adjustments made, so they can be
+/// reported.
+pub fn insert_required_breaks(time_sheet_entries: &mut [TimeSheetEntry]) -> Vec<BreakAdjustment> {
+    let noon = NaiveTime::from_hms_opt(12, 0, 0).expect("valid time");
+    let mut adjustments = vec![];
+    for (date, range) in days(time_sheet_entries) {
+        let entries = &mut time_sheet_entries[range];
+        let working_time = working_time(entries);
+        let shortfall = required_break(working_time) - break_time(entries);
+        if shortfall <= Duration::zero() {
+            continue;
+        }
+        let added = shortfall.max(Duration::minutes(MIN_BREAK_SEGMENT));
+
+        let duration = |entry: &TimeSheetEntry| entry.end - entry.start - entry.break_;
+        let mut gaps: Vec<usize> = (1..entries.len())
+            .filter(|index| entries[*index].start > entries[index - 1].end)
+            .collect();
+        gaps.sort_by_key(|index| std::cmp::Reverse(entries[*index].start - entries[index - 1].end));
+        let gap = gaps.into_iter().find_map(|index| {
+            if duration(&entries[index - 1]) >= added {
+                Some(index - 1)
+            } else if duration(&entries[index]) >= added {
+                Some(index)
+            } else {
+                None
+            }
+            .map(|shortened| (index, shortened))
+        });
+        let description = if let Some((index, shortened)) = gap {
+            if shortened < index {
+                entries[shortened].end -= added;
+            } else {
+                entries[shortened].start += added;
+            }
+            entries[shortened].description.clone()
+        } else {
+            let spans_noon = |entry: &TimeSheetEntry| {
+                let noon = date.and_time(noon);
+                entry.start.naive_local() <= noon && noon < entry.end.naive_local()
+            };
+            let mut candidates: Vec<usize> = (0..entries.len()).collect();
+            candidates.sort_by_key(|index| {
+                let entry = &entries[*index];
+                (!spans_noon(entry), std::cmp::Reverse(duration(entry)))
+            });
+            let Some(index) = candidates
+                .into_iter()
+                .find(|index| duration(&entries[*index]) >= added)
+            else {
+                continue;
+            };
+            let entry = &mut entries[index];
+            entry.break_ = entry.break_ + added;
+            entry.description.clone()
+        };
+        adjustments.push(BreakAdjustment {
+            date,
+            description,
+            added,
+            working_time: working_time - added,
+        });
+    }
+    adjustments
+}
+
+/// Ranges of the entries of each day, which are ordered by start.
+fn days(time_sheet_entries: &[TimeSheetEntry]) -> Vec<(NaiveDate, Range<usize>)> {
+    let mut days: Vec<(NaiveDate, Range<usize>)> = vec![];
+    for (index, entry) in time_sheet_entries.iter().enumerate() {
+        let date = entry.start.date_naive();
+        match days.last_mut() {
+            Some((last_date, range)) if *last_date == date => range.end = index + 1,
+            _ => days.push((date, index..index + 1)),
+        }
+    }
+    days
+}
+
+fn working_time(entries: &[TimeSheetEntry]) -> Duration {
+    entries.iter().fold(Duration::zero(), |total, entry| {
+        total + (entry.end - entry.start - entry.break_)
+    })
+}
+
+/// Breaks of the entries plus the gaps between them, each only counting as
+/// break if it lasts at least 15 minutes.
+fn break_time(entries: &[TimeSheetEntry]) -> Duration {
+    let gaps = entries.windows(2).map(|pair| pair[1].start - pair[0].end);
+    entries
+        .iter()
+        .map(|entry| entry.break_)
+        .chain(gaps)
+        .filter(|break_| *break_ >= Duration::minutes(MIN_BREAK_SEGMENT))
+        .fold(Duration::zero(), |total, break_| total + break_)
+}
+
+/// Minimum total break for the given working time (§ 4 ArbZG).
+fn required_break(working_time: Duration) -> Duration {
+    if working_time > Duration::hours(9) {
+        Duration::minutes(45)
+    } else if working_time > Duration::hours(6) {
+        Duration::minutes(30)
+    } else {
+        Duration::zero()
+    }
+}
+
+/// Format a duration as `h:mm` with sign for negative values, e.g. the rest
+/// period before overlapping days.
+fn format_duration(duration: &Duration) -> String {
+    let minutes = duration.num_minutes();
+    let sign = if minutes < 0 { "-" } else { "" };
+    let minutes = minutes.abs();
+    format!("{sign}{}:{:02}", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        source::Entry,
+        transform::{
+            lay_out_time_sheet_entries, worked_time_sheet_entries, Aggregation, TransformOptions,
+        },
+    };
+    use chrono::{Local, TimeZone};
+
+    fn entry(day: u32, start: (u32, u32), end: (u32, u32), break_: i64) -> TimeSheetEntry {
+        TimeSheetEntry {
+            description: "Task 1".to_string(),
+            task: None,
+            project: None,
+            tags: vec![],
+            billable: false,
+            start: Local
+                .with_ymd_and_hms(2022, 10, day, start.0, start.1, 0)
+                .unwrap(),
+            end: Local
+                .with_ymd_and_hms(2022, 10, day, end.0, end.1, 0)
+                .unwrap(),
+            break_: Duration::minutes(break_),
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2022, 10, day).unwrap()
+    }
+
+    #[test]
+    fn test_compliant_time_sheet() {
+        let entries = vec![
+            // 9 hours with two breaks of 15 and 20 minutes.
+            entry(4, (7, 0), (12, 0), 15),
+            entry(4, (12, 20), (16, 35), 0),
+            entry(5, (8, 0), (14, 0), 0),
+        ];
+        assert_eq!(
+            check_working_time_law(&entries, &Holidays::default()),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_working_time_violations() {
+        let entries = vec![
+            // Sunday without breaks, as the gap is shorter than 15 minutes.
+            entry(2, (8, 0), (12, 0), 0),
+            entry(2, (12, 10), (16, 10), 0),
+            // Holiday starting after 9:45 rest with 17:30 working time.
+            entry(3, (1, 55), (8, 0), 0),
+            entry(3, (9, 0), (20, 45), 20),
+            entry(4, (7, 30), (9, 0), 0),
+        ];
+        let holidays = Holidays {
+            region: Some("DE".parse().unwrap()),
+            ..Default::default()
+        };
+        let violations = check_working_time_law(&entries, &holidays);
+        assert_eq!(
+            violations,
+            vec![
+                Violation {
+                    date: date(2),
+                    kind: ViolationKind::InsufficientBreak {
+                        working_time: Duration::hours(8),
+                        break_: Duration::zero(),
+                        required: Duration::minutes(30),
+                    },
+                },
+                Violation {
+                    date: date(2),
+                    kind: ViolationKind::SundayWork,
+                },
+                Violation {
+                    date: date(3),
+                    kind: ViolationKind::ExcessiveWorkingTime {
+                        working_time: Duration::minutes(17 * 60 + 30),
+                    },
+                },
+                Violation {
+                    date: date(3),
+                    kind: ViolationKind::InsufficientRest {
+                        rest: Duration::minutes(9 * 60 + 45),
+                    },
+                },
+                Violation {
+                    date: date(3),
+                    kind: ViolationKind::HolidayWork {
+                        holiday: "Tag der Deutschen Einheit".to_string(),
+                    },
+                },
+                Violation {
+                    date: date(4),
+                    kind: ViolationKind::InsufficientRest {
+                        rest: Duration::minutes(10 * 60 + 45),
+                    },
+                },
+            ]
+        );
+        assert_eq!(
+            violations[0].to_string(),
+            "02.10.22: Break of 0:00 is shorter than the required 0:30 for 8:00 working time"
+        );
+        assert_eq!(
+            violations[3].to_string(),
+            "03.10.22: Rest period of 9:45 since the previous day is shorter than 11 hours"
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(&Duration::minutes(90)), "1:30");
+        assert_eq!(format_duration(&Duration::minutes(-90)), "-1:30");
+        assert_eq!(format_duration(&Duration::minutes(-30)), "-0:30");
+    }
+
+    #[test]
+    fn test_check_worked_entries_aggregated_per_task_per_day() {
+        let time_entry = |description: &str, start: u32, end: (u32, u32)| Entry {
+            description: description.to_string(),
+            project: None,
+            client: None,
+            task: None,
+            tags: vec![],
+            billable: false,
+            start: Local.with_ymd_and_hms(2022, 10, 4, start, 0, 0).unwrap(),
+            end: Local
+                .with_ymd_and_hms(2022, 10, 4, end.0, end.1, 0)
+                .unwrap(),
+        };
+        // 7:30 hours of work with a break of one hour.
+        let time_entries = vec![
+            time_entry("Task 1", 8, (12, 0)),
+            time_entry("Task 2", 13, (15, 0)),
+            time_entry("Task 1", 15, (16, 30)),
+        ];
+        let options = TransformOptions {
+            aggregation: Aggregation::PerTaskPerDay,
+            ..Default::default()
+        };
+        let mut worked = worked_time_sheet_entries(time_entries, &options).unwrap();
+        assert_eq!(
+            check_working_time_law(&worked, &Holidays::default()),
+            vec![]
+        );
+        assert_eq!(insert_required_breaks(&mut worked), vec![]);
+
+        // The rows are laid out back-to-back, so they lack the break.
+        let rows = lay_out_time_sheet_entries(worked, &options);
+        assert_eq!(rows.len(), 2);
+        assert!(matches!(
+            check_working_time_law(&rows, &Holidays::default())[..],
+            [Violation {
+                kind: ViolationKind::InsufficientBreak { .. },
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_insert_required_breaks() {
+        let mut entries = vec![
+            // The entry spanning noon gets the whole break, as the break of
+            // 10 minutes does not count.
+            entry(4, (7, 0), (9, 0), 0),
+            entry(4, (9, 0), (16, 0), 10),
+            // The gap of 5 minutes is extended to a break of 35 minutes.
+            entry(5, (8, 0), (11, 0), 0),
+            entry(5, (11, 5), (14, 15), 0),
+            // Enough break in the gap.
+            entry(6, (8, 0), (12, 0), 0),
+            entry(6, (12, 30), (16, 30), 0),
+            // At least 15 minutes are added for the missing 15 minutes.
+            entry(7, (7, 0), (12, 0), 0),
+            entry(7, (12, 30), (17, 0), 0),
+        ];
+        let adjustments = insert_required_breaks(&mut entries);
+        assert_eq!(
+            adjustments,
+            vec![
+                BreakAdjustment {
+                    date: date(4),
+                    description: "Task 1".to_string(),
+                    added: Duration::minutes(30),
+                    working_time: Duration::minutes(8 * 60 + 20),
+                },
+                BreakAdjustment {
+                    date: date(5),
+                    description: "Task 1".to_string(),
+                    added: Duration::minutes(30),
+                    working_time: Duration::minutes(5 * 60 + 40),
+                },
+                BreakAdjustment {
+                    date: date(7),
+                    description: "Task 1".to_string(),
+                    added: Duration::minutes(15),
+                    working_time: Duration::minutes(9 * 60 + 15),
+                },
+            ]
+        );
+        let breaks = entries
+            .iter()
+            .map(|entry| entry.break_.num_minutes())
+            .collect::<Vec<_>>();
+        assert_eq!(breaks, vec![0, 40, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            entries[2].end,
+            Local.with_ymd_and_hms(2022, 10, 5, 10, 30, 0).unwrap()
+        );
+        assert_eq!(
+            entries[6].end,
+            Local.with_ymd_and_hms(2022, 10, 7, 11, 45, 0).unwrap()
+        );
+        assert_eq!(
+            check_working_time_law(&entries, &Holidays::default()),
+            vec![]
+        );
+        assert_eq!(
+            adjustments[0].to_string(),
+            "04.10.22: Added break of 0:30 to \"Task 1\" as required for 8:20 working time"
+        );
+    }
+}
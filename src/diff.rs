@@ -0,0 +1,244 @@
+use crate::{
+    reader::{read_csv, ReaderError},
+    transform::TimeSheetEntry,
+    writer::write_csv,
+};
+use chrono::Duration;
+use std::fmt;
+
+/// Differences between an existing and a regenerated time sheet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SheetDiff {
+    /// Rows only present in the regenerated time sheet.
+    pub added: Vec<TimeSheetEntry>,
+    /// Rows only present in the existing time sheet.
+    pub removed: Vec<TimeSheetEntry>,
+    /// Rows present in both time sheets with different values as pairs of
+    /// existing and regenerated row.
+    pub changed: Vec<(TimeSheetEntry, TimeSheetEntry)>,
+    /// Net change of the total working time.
+    pub total_change: Duration,
+}
+
+impl SheetDiff {
+    /// Whether the time sheets are equal.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare an existing time sheet with a regenerated one.
+/// The regenerated entries are compared as they would be written, i.e. with
+/// times rounded to minutes. Rows which are not equal are considered changed,
+/// if a row of the other sheet has the same date and description or, failing
+/// that, the same date and start time. All other rows are added or removed.
+pub fn diff_time_sheets(
+    existing: &[TimeSheetEntry],
+    regenerated: &[TimeSheetEntry],
+) -> Result<SheetDiff, ReaderError> {
+    let regenerated = as_written(regenerated)?;
+
+    // Drop rows present in both sheets.
+    let mut removed: Vec<TimeSheetEntry> = vec![];
+    let mut added: Vec<TimeSheetEntry> = regenerated.clone();
+    for entry in existing {
+        if let Some(index) = added.iter().position(|other| same_row(entry, other)) {
+            added.remove(index);
+        } else {
+            removed.push(entry.clone());
+        }
+    }
+
+    // Pair remaining rows as changed rows.
+    let mut changed = vec![];
+    let is_match: [fn(&TimeSheetEntry, &TimeSheetEntry) -> bool; 2] = [
+        |a, b| a.start.date_naive() == b.start.date_naive() && a.description == b.description,
+        |a, b| a.start == b.start,
+    ];
+    for is_match in is_match {
+        let mut unmatched = vec![];
+        for entry in removed {
+            if let Some(index) = added.iter().position(|other| is_match(&entry, other)) {
+                changed.push((entry, added.remove(index)));
+            } else {
+                unmatched.push(entry);
+            }
+        }
+        removed = unmatched;
+    }
+    changed.sort_by_key(|(entry, _)| entry.start);
+
+    Ok(SheetDiff {
+        added,
+        removed,
+        changed,
+        total_change: working_time(&regenerated) - working_time(existing),
+    })
+}
+
+/// Total working time of the given time sheet entries, i.e. the time between
+/// start and end of each entry reduced by its break.
+pub fn working_time(time_sheet_entries: &[TimeSheetEntry]) -> Duration {
+    time_sheet_entries
+        .iter()
+        .fold(Duration::zero(), |total, entry| {
+            total + (entry.end - entry.start) - entry.break_
+        })
+}
+
+/// Convert entries into the representation they would have after writing them
+/// to a time sheet and reading them back in.
+fn as_written(time_sheet_entries: &[TimeSheetEntry]) -> Result<Vec<TimeSheetEntry>, ReaderError> {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_csv(&mut buffer, time_sheet_entries)?;
+    read_csv(buffer.as_slice())
+}
+
+/// Compare all fields of time sheet entries which are written to the sheet.
+fn same_row(a: &TimeSheetEntry, b: &TimeSheetEntry) -> bool {
+    a.start == b.start && a.end == b.end && a.break_ == b.break_ && a.description == b.description
+}
+
+impl fmt::Display for SheetDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.removed {
+            writeln!(f, "- {}", DisplayRow(entry))?;
+        }
+        for entry in &self.added {
+            writeln!(f, "+ {}", DisplayRow(entry))?;
+        }
+        for (existing, regenerated) in &self.changed {
+            writeln!(f, "~ {}", DisplayRow(existing))?;
+            writeln!(f, "  {}", DisplayRow(regenerated))?;
+        }
+        let sign = if self.total_change > Duration::zero() {
+            "+"
+        } else {
+            ""
+        };
+        write!(
+            f,
+            "Net change: {sign}{} h",
+            format_duration(&self.total_change)
+        )
+    }
+}
+
+/// Helper for displaying a time sheet entry as a single line.
+struct DisplayRow<'a>(&'a TimeSheetEntry);
+
+impl fmt::Display for DisplayRow<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entry = self.0;
+        write!(
+            f,
+            "{} {}-{}",
+            entry.start.format("%d.%m.%y"),
+            entry.start.format("%H:%M"),
+            entry.end.format("%H:%M"),
+        )?;
+        if entry.break_ > Duration::zero() {
+            write!(f, " (break {})", format_duration(&entry.break_))?;
+        }
+        write!(f, " {}", entry.description)
+    }
+}
+
+/// Format a duration in minute precision as h:mm with sign for negative values.
+fn format_duration(duration: &Duration) -> String {
+    let minutes = duration.num_minutes();
+    let sign = if minutes < 0 { "-" } else { "" };
+    let minutes = minutes.abs();
+    format!("{sign}{}:{:02}", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::prelude::*;
+
+    fn entry(description: &str, day: u32, start: (u32, u32), end: (u32, u32)) -> TimeSheetEntry {
+        TimeSheetEntry {
+            description: description.to_string(),
+            task: None,
+            project: None,
+            tags: vec![],
+            billable: false,
+            start: Local
+                .with_ymd_and_hms(2022, 10, day, start.0, start.1, 0)
+                .unwrap(),
+            end: Local
+                .with_ymd_and_hms(2022, 10, day, end.0, end.1, 0)
+                .unwrap(),
+            break_: Duration::zero(),
+        }
+    }
+
+    #[test]
+    fn test_diff_equal_sheets_after_rounding() {
+        let existing = vec![entry("Task 1", 1, (8, 0), (9, 0))];
+        let mut regenerated = existing.clone();
+        regenerated[0].start += Duration::seconds(29);
+        regenerated[0].task = Some("Task 1".to_string());
+        let result = diff_time_sheets(&existing, &regenerated).unwrap();
+        assert!(result.is_empty());
+        assert_eq!(result.total_change, Duration::zero());
+    }
+
+    #[test]
+    fn test_diff_added_removed_and_changed_rows() {
+        let existing = vec![
+            entry("Task 1", 1, (8, 0), (9, 0)),
+            entry("Task 2", 1, (9, 0), (10, 0)),
+            entry("Task 3", 2, (8, 0), (9, 0)),
+            entry("Task 4", 3, (8, 0), (9, 0)),
+        ];
+        let regenerated = vec![
+            entry("Task 1", 1, (8, 0), (9, 0)),
+            entry("Task 2", 1, (9, 0), (10, 30)),
+            entry("Task 5", 2, (8, 0), (9, 0)),
+            entry("Task 6", 4, (8, 0), (8, 15)),
+        ];
+        let result = diff_time_sheets(&existing, &regenerated).unwrap();
+        assert_eq!(result.removed, vec![existing[3].clone()]);
+        assert_eq!(result.added, vec![regenerated[3].clone()]);
+        assert_eq!(
+            result.changed,
+            vec![
+                (existing[1].clone(), regenerated[1].clone()),
+                (existing[2].clone(), regenerated[2].clone()),
+            ]
+        );
+        // +30 min for task 2, -60 min for task 4, +15 min for task 6.
+        assert_eq!(result.total_change, Duration::minutes(-15));
+    }
+
+    #[test]
+    fn test_working_time() {
+        let mut entries = vec![
+            entry("Task 1", 1, (8, 0), (12, 0)),
+            entry("Task 2", 1, (13, 0), (15, 0)),
+        ];
+        entries[0].break_ = Duration::minutes(30);
+        assert_eq!(working_time(&entries), Duration::minutes(330));
+    }
+
+    #[test]
+    fn test_display() {
+        let existing = vec![entry("Task 1", 1, (8, 0), (9, 0))];
+        let mut regenerated = vec![entry("Task 1", 1, (8, 0), (9, 30))];
+        regenerated[0].break_ = Duration::minutes(15);
+        let result = diff_time_sheets(&existing, &regenerated).unwrap();
+        assert_eq!(
+            result.to_string(),
+            "~ 01.10.22 08:00-09:00 Task 1\n  01.10.22 08:00-09:30 (break 0:15) Task 1\nNet change: +0:15 h"
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(&Duration::zero()), "0:00");
+        assert_eq!(format_duration(&Duration::minutes(75)), "1:15");
+        assert_eq!(format_duration(&Duration::minutes(-15)), "-0:15");
+    }
+}
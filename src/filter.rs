@@ -0,0 +1,212 @@
+use crate::source::Entry;
+use regex::Regex;
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FilterError {
+    #[error("Invalid description pattern")]
+    InvalidPattern(#[from] regex::Error),
+}
+
+/// Criteria of include or exclude rules. Names are compared exactly, while
+/// descriptions are matched against regular expressions.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct FilterRules {
+    pub tasks: Vec<String>,
+    pub tags: Vec<String>,
+    pub projects: Vec<String>,
+    pub clients: Vec<String>,
+    pub descriptions: Vec<String>,
+}
+
+impl FilterRules {
+    /// Append the criteria of the other rules to these rules.
+    pub fn extend(&mut self, other: FilterRules) {
+        self.tasks.extend(other.tasks);
+        self.tags.extend(other.tags);
+        self.projects.extend(other.projects);
+        self.clients.extend(other.clients);
+        self.descriptions.extend(other.descriptions);
+    }
+}
+
+/// Include and exclude rules selecting the time entries of the time sheet,
+/// e.g. configured in the `[filter.include]` and `[filter.exclude]` tables of
+/// the config file.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct FilterConfig {
+    pub include: FilterRules,
+    pub exclude: FilterRules,
+}
+
+impl FilterConfig {
+    /// Append the rules of the other configuration to this configuration.
+    pub fn extend(&mut self, other: FilterConfig) {
+        self.include.extend(other.include);
+        self.exclude.extend(other.exclude);
+    }
+
+    /// Compile the description patterns of the rules into a filter.
+    pub fn build(&self) -> Result<Filter, FilterError> {
+        Ok(Filter {
+            include: CompiledRules::new(&self.include)?,
+            exclude: CompiledRules::new(&self.exclude)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CompiledRules {
+    tasks: Vec<String>,
+    tags: Vec<String>,
+    projects: Vec<String>,
+    clients: Vec<String>,
+    descriptions: Vec<Regex>,
+}
+
+impl CompiledRules {
+    fn new(rules: &FilterRules) -> Result<Self, FilterError> {
+        Ok(CompiledRules {
+            tasks: rules.tasks.clone(),
+            tags: rules.tags.clone(),
+            projects: rules.projects.clone(),
+            clients: rules.clients.clone(),
+            descriptions: rules
+                .descriptions
+                .iter()
+                .map(|pattern| Regex::new(pattern))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Results of matching the entry against each criterion, `None` for
+    /// criteria without values.
+    fn criteria(&self, entry: &Entry) -> [Option<bool>; 5] {
+        fn contains(values: &[String], value: Option<&String>) -> Option<bool> {
+            (!values.is_empty()).then(|| value.is_some_and(|value| values.contains(value)))
+        }
+        [
+            contains(&self.tasks, entry.task.as_ref()),
+            (!self.tags.is_empty()).then(|| entry.tags.iter().any(|tag| self.tags.contains(tag))),
+            contains(&self.projects, entry.project.as_ref()),
+            contains(&self.clients, entry.client.as_ref()),
+            (!self.descriptions.is_empty()).then(|| {
+                self.descriptions
+                    .iter()
+                    .any(|pattern| pattern.is_match(&entry.description))
+            }),
+        ]
+    }
+}
+
+/// Filter applied to the retrieved time entries before they are transformed.
+/// - A time entry is included, if it matches every criterion of the include
+///   rules, i.e. any of the values given for each criterion. Criteria without
+///   values are ignored.
+/// - A time entry is excluded, if it matches any value of the exclude rules.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    include: CompiledRules,
+    exclude: CompiledRules,
+}
+
+impl Filter {
+    /// Check whether the time entry passes the filter.
+    pub fn matches(&self, entry: &Entry) -> bool {
+        self.include
+            .criteria(entry)
+            .into_iter()
+            .all(|matches| matches.unwrap_or(true))
+            && !self
+                .exclude
+                .criteria(entry)
+                .into_iter()
+                .any(|matches| matches.unwrap_or(false))
+    }
+
+    /// Keep only the time entries passing the filter.
+    pub fn apply(&self, mut entries: Vec<Entry>) -> Vec<Entry> {
+        entries.retain(|entry| self.matches(entry));
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    fn entry(description: &str, task: Option<&str>, tags: &[&str], client: &str) -> Entry {
+        Entry {
+            description: description.to_string(),
+            project: Some("Project".to_string()),
+            client: Some(client.to_string()),
+            task: task.map(str::to_owned),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            billable: false,
+            start: Local.with_ymd_and_hms(2022, 10, 1, 8, 0, 0).unwrap(),
+            end: Local.with_ymd_and_hms(2022, 10, 1, 9, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_filter_include_and_exclude_rules() {
+        let entries = vec![
+            entry("Ticket #1", Some("Support"), &["customer-x"], "Client X"),
+            entry("Ticket #2", Some("Support"), &["customer-y"], "Client Y"),
+            entry("Call", Some("Support"), &["customer-x"], "Client X"),
+            entry("Ticket #3", None, &["customer-x"], "Client X"),
+            entry("Ticket #4", Some("Development"), &[], "Client X"),
+        ];
+        let filter = FilterConfig {
+            include: FilterRules {
+                tasks: vec!["Support".to_string()],
+                clients: vec!["Client X".to_string(), "Client Y".to_string()],
+                ..Default::default()
+            },
+            exclude: FilterRules {
+                tags: vec!["customer-y".to_string()],
+                descriptions: vec!["^Call".to_string()],
+                ..Default::default()
+            },
+        }
+        .build()
+        .unwrap();
+        let result = filter.apply(entries.clone());
+        assert_eq!(result, vec![entries[0].clone()]);
+    }
+
+    #[test]
+    fn test_empty_filter_matches_all_entries() {
+        let filter = FilterConfig::default().build().unwrap();
+        assert!(filter.matches(&entry("Ticket #1", None, &[], "Client X")));
+    }
+
+    #[test]
+    fn test_filter_config_from_toml() {
+        let config: FilterConfig = toml::from_str(
+            r#"
+            include = { tasks = ["Support"] }
+            [exclude]
+            descriptions = ["(?i)internal"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.include.tasks, vec!["Support"]);
+        assert_eq!(config.exclude.descriptions, vec!["(?i)internal"]);
+        assert!(matches!(
+            FilterConfig {
+                exclude: FilterRules {
+                    descriptions: vec!["(".to_string()],
+                    ..Default::default()
+                },
+                ..config
+            }
+            .build(),
+            Err(FilterError::InvalidPattern(_))
+        ));
+    }
+}
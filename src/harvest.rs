@@ -0,0 +1,363 @@
+use crate::source::{Entry, Query, SourceError, TimeSource};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, TimeZone};
+use serde::Deserialize;
+use std::collections::HashMap;
+use thiserror::Error;
+
+static HARVEST_API_BASE: &str = "https://api.harvestapp.com";
+/// Time formats used by Harvest depending on the account settings.
+const TIME_FORMATS: [&str; 2] = ["%H:%M", "%I:%M%p"];
+
+#[derive(Debug, Error)]
+pub enum HarvestError {
+    #[error("REST API error")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("JSON deserialization error")]
+    Deserialization(#[from] serde_json::Error),
+    #[error("Invalid time '{0}'")]
+    InvalidTime(String),
+}
+
+/// Time source retrieving the time entries of the authenticated user via the
+/// Harvest API v2. Configured in the `[harvest]` table of the config file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct HarvestSource {
+    pub account_id: String,
+    pub access_token: String,
+    /// Only retrieve time entries of this project, if given.
+    #[serde(default)]
+    pub project_id: Option<u64>,
+    /// Start of the day for time entries tracked as duration only, which are
+    /// placed one after another from this time on.
+    #[serde(default = "default_day_start")]
+    pub day_start: NaiveTime,
+    /// Base URL of the API, only to be changed for testing.
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+}
+
+fn default_day_start() -> NaiveTime {
+    NaiveTime::from_hms_opt(9, 0, 0).unwrap()
+}
+
+fn default_base_url() -> String {
+    HARVEST_API_BASE.to_owned()
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Reference {
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct TimeEntry {
+    pub id: u64,
+    pub spent_date: NaiveDate,
+    pub hours: f64,
+    pub started_time: Option<String>,
+    pub ended_time: Option<String>,
+    #[serde(default)]
+    pub is_running: bool,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub billable: bool,
+    pub project: Option<Reference>,
+    pub task: Option<Reference>,
+    #[serde(default)]
+    pub client: Option<Reference>,
+}
+
+/// Page of the time entries list.
+#[derive(Debug, Deserialize)]
+struct TimeEntriesPage {
+    time_entries: Vec<TimeEntry>,
+    next_page: Option<u32>,
+}
+
+#[async_trait]
+impl TimeSource for HarvestSource {
+    async fn retrieve(&self, query: &Query) -> Result<Vec<Entry>, SourceError> {
+        let time_entries = self.retrieve_time_entries(query).await?;
+        let mut entries = convert_time_entries(time_entries, self.day_start)?;
+        entries.retain(|entry| query.matches(entry));
+        entries.sort_by_key(|entry| entry.start);
+        Ok(entries)
+    }
+}
+
+impl HarvestSource {
+    /// Retrieve all time entries of the queried days, following the pagination
+    /// of the API.
+    pub async fn retrieve_time_entries(
+        &self,
+        query: &Query,
+    ) -> Result<Vec<TimeEntry>, HarvestError> {
+        let client = reqwest::ClientBuilder::new()
+            .user_agent("clockify-to-time-sheet")
+            .build()?;
+        // The `to` date is inclusive.
+        let from = query.start.format("%Y-%m-%d").to_string();
+        let to = (query.end - Duration::days(1))
+            .format("%Y-%m-%d")
+            .to_string();
+        let mut time_entries: Vec<TimeEntry> = vec![];
+        let mut page = Some(1);
+        while let Some(current_page) = page {
+            let mut request = client
+                .get(format!("{}/v2/time_entries", self.base_url))
+                .bearer_auth(&self.access_token)
+                .header("Harvest-Account-Id", &self.account_id)
+                .query(&[
+                    ("from", from.clone()),
+                    ("to", to.clone()),
+                    ("page", current_page.to_string()),
+                ]);
+            if let Some(project_id) = self.project_id {
+                request = request.query(&[("project_id", project_id)]);
+            }
+            let response = request.send().await?.error_for_status()?;
+            let response_body = response.text().await?;
+            let time_entries_page: TimeEntriesPage = serde_json::from_str(&response_body)?;
+            time_entries.extend(time_entries_page.time_entries);
+            page = time_entries_page.next_page;
+        }
+        Ok(time_entries)
+    }
+}
+
+/// Convert Harvest time entries into normalized entries. Running entries are
+/// skipped. Entries tracked as duration only are placed one after another
+/// starting at `day_start`, in the order they were created.
+fn convert_time_entries(
+    mut time_entries: Vec<TimeEntry>,
+    day_start: NaiveTime,
+) -> Result<Vec<Entry>, HarvestError> {
+    time_entries.retain(|time_entry| !time_entry.is_running);
+    time_entries.sort_by_key(|time_entry| (time_entry.spent_date, time_entry.id));
+    let mut next_start: HashMap<NaiveDate, DateTime<Local>> = HashMap::new();
+    time_entries
+        .into_iter()
+        .map(|time_entry| {
+            let date = time_entry.spent_date;
+            let (start, end) = match (&time_entry.started_time, &time_entry.ended_time) {
+                (Some(start), Some(end)) => {
+                    let start = local(date, parse_time(start)?)?;
+                    let mut end = local(date, parse_time(end)?)?;
+                    if end < start {
+                        end += Duration::days(1);
+                    }
+                    (start, end)
+                }
+                _ => {
+                    let start = match next_start.get(&date) {
+                        Some(start) => *start,
+                        None => local(date, day_start)?,
+                    };
+                    let seconds = (time_entry.hours * 3600.0).round() as i64;
+                    let end = start + Duration::seconds(seconds);
+                    next_start.insert(date, end);
+                    (start, end)
+                }
+            };
+            Ok(Entry {
+                description: time_entry.notes.unwrap_or_default(),
+                project: time_entry.project.map(|project| project.name),
+                client: time_entry.client.map(|client| client.name),
+                task: time_entry.task.map(|task| task.name),
+                tags: vec![],
+                billable: time_entry.billable,
+                start,
+                end,
+            })
+        })
+        .collect()
+}
+
+/// Parse a time of day like `08:00` or `8:00am`.
+fn parse_time(value: &str) -> Result<NaiveTime, HarvestError> {
+    TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(value, format).ok())
+        .ok_or_else(|| HarvestError::InvalidTime(value.to_owned()))
+}
+
+/// Combine date and time to a local date time.
+fn local(date: NaiveDate, time: NaiveTime) -> Result<DateTime<Local>, HarvestError> {
+    Local
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .ok_or_else(|| HarvestError::InvalidTime(time.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn time_entry(id: u64, spent_date: &str, hours: f64, times: Option<(&str, &str)>) -> TimeEntry {
+        TimeEntry {
+            id,
+            spent_date: spent_date.parse().unwrap(),
+            hours,
+            started_time: times.map(|(start, _)| start.to_string()),
+            ended_time: times.map(|(_, end)| end.to_string()),
+            is_running: false,
+            notes: Some(format!("Entry {id}")),
+            billable: true,
+            project: Some(Reference {
+                id: 1,
+                name: "Project".to_string(),
+            }),
+            task: Some(Reference {
+                id: 2,
+                name: "Development".to_string(),
+            }),
+            client: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_time() {
+        let expected_result = NaiveTime::from_hms_opt(8, 5, 0).unwrap();
+        assert_eq!(parse_time("08:05").unwrap(), expected_result);
+        assert_eq!(parse_time("8:05am").unwrap(), expected_result);
+        assert_eq!(
+            parse_time("1:30pm").unwrap(),
+            NaiveTime::from_hms_opt(13, 30, 0).unwrap()
+        );
+        assert!(matches!(
+            parse_time("8h"),
+            Err(HarvestError::InvalidTime(_))
+        ));
+    }
+
+    #[test]
+    fn test_convert_time_entries() {
+        let mut running = time_entry(5, "2022-10-01", 0.5, None);
+        running.is_running = true;
+        let time_entries = vec![
+            time_entry(4, "2022-10-02", 1.0, Some(("1:00pm", "2:00pm"))),
+            time_entry(3, "2022-10-01", 0.25, None),
+            time_entry(2, "2022-10-01", 1.5, None),
+            running,
+            time_entry(1, "2022-10-01", 1.0, Some(("23:30", "00:30"))),
+        ];
+        let day_start = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        let result = convert_time_entries(time_entries, day_start).unwrap();
+        let times: Vec<_> = result
+            .iter()
+            .map(|entry| (entry.description.as_str(), entry.start, entry.end))
+            .collect();
+        assert_eq!(
+            times,
+            vec![
+                (
+                    "Entry 1",
+                    Local.with_ymd_and_hms(2022, 10, 1, 23, 30, 0).unwrap(),
+                    Local.with_ymd_and_hms(2022, 10, 2, 0, 30, 0).unwrap(),
+                ),
+                (
+                    "Entry 2",
+                    Local.with_ymd_and_hms(2022, 10, 1, 9, 0, 0).unwrap(),
+                    Local.with_ymd_and_hms(2022, 10, 1, 10, 30, 0).unwrap(),
+                ),
+                (
+                    "Entry 3",
+                    Local.with_ymd_and_hms(2022, 10, 1, 10, 30, 0).unwrap(),
+                    Local.with_ymd_and_hms(2022, 10, 1, 10, 45, 0).unwrap(),
+                ),
+                (
+                    "Entry 4",
+                    Local.with_ymd_and_hms(2022, 10, 2, 13, 0, 0).unwrap(),
+                    Local.with_ymd_and_hms(2022, 10, 2, 14, 0, 0).unwrap(),
+                ),
+            ]
+        );
+        assert_eq!(result[0].project, Some("Project".to_string()));
+        assert_eq!(result[0].task, Some("Development".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_paginated_time_entries() {
+        let server = MockServer::start().await;
+        let entry = |id: u64, spent_date: &str| {
+            serde_json::json!({
+                "id": id,
+                "spent_date": spent_date,
+                "hours": 1.0,
+                "started_time": "8:00am",
+                "ended_time": "9:00am",
+                "is_running": false,
+                "notes": format!("Entry {id}"),
+                "billable": false,
+                "project": { "id": 1, "name": "Project" },
+                "task": { "id": 2, "name": "Development" },
+                "client": { "id": 3, "name": "Client" }
+            })
+        };
+        Mock::given(method("GET"))
+            .and(path("/v2/time_entries"))
+            .and(header("Authorization", "Bearer token"))
+            .and(header("Harvest-Account-Id", "123"))
+            .and(query_param("from", "2022-10-01"))
+            .and(query_param("to", "2022-10-31"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "time_entries": [entry(2, "2022-10-02")],
+                "per_page": 1,
+                "next_page": 2
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/time_entries"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "time_entries": [entry(1, "2022-10-01")],
+                "per_page": 1,
+                "next_page": null
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let source = HarvestSource {
+            account_id: "123".to_string(),
+            access_token: "token".to_string(),
+            project_id: None,
+            day_start: default_day_start(),
+            base_url: server.uri(),
+        };
+        let query = Query::month(2022, 10).unwrap();
+        let result = source.retrieve(&query).await.unwrap();
+        let expected_result = vec![
+            Entry {
+                description: "Entry 1".to_string(),
+                project: Some("Project".to_string()),
+                client: Some("Client".to_string()),
+                task: Some("Development".to_string()),
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 8, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 9, 0, 0).unwrap(),
+            },
+            Entry {
+                description: "Entry 2".to_string(),
+                project: Some("Project".to_string()),
+                client: Some("Client".to_string()),
+                task: Some("Development".to_string()),
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 2, 8, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 2, 9, 0, 0).unwrap(),
+            },
+        ];
+        assert_eq!(result, expected_result);
+    }
+}
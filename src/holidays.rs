@@ -0,0 +1,339 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::Deserialize;
+use std::{collections::BTreeMap, fmt, str::FromStr};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum HolidayError {
+    #[error("Unknown holiday region: {0}")]
+    UnknownRegion(String),
+}
+
+/// Federal state of Germany, identified by its ISO 3166-2 code, e.g. `BY`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GermanState {
+    BadenWuerttemberg,
+    Bayern,
+    Berlin,
+    Brandenburg,
+    Bremen,
+    Hamburg,
+    Hessen,
+    MecklenburgVorpommern,
+    Niedersachsen,
+    NordrheinWestfalen,
+    RheinlandPfalz,
+    Saarland,
+    Sachsen,
+    SachsenAnhalt,
+    SchleswigHolstein,
+    Thueringen,
+}
+
+impl GermanState {
+    /// All federal states.
+    pub const ALL: [GermanState; 16] = [
+        GermanState::BadenWuerttemberg,
+        GermanState::Bayern,
+        GermanState::Berlin,
+        GermanState::Brandenburg,
+        GermanState::Bremen,
+        GermanState::Hamburg,
+        GermanState::Hessen,
+        GermanState::MecklenburgVorpommern,
+        GermanState::Niedersachsen,
+        GermanState::NordrheinWestfalen,
+        GermanState::RheinlandPfalz,
+        GermanState::Saarland,
+        GermanState::Sachsen,
+        GermanState::SachsenAnhalt,
+        GermanState::SchleswigHolstein,
+        GermanState::Thueringen,
+    ];
+
+    /// ISO 3166-2 code of the state without country prefix.
+    pub fn code(&self) -> &'static str {
+        match self {
+            GermanState::BadenWuerttemberg => "BW",
+            GermanState::Bayern => "BY",
+            GermanState::Berlin => "BE",
+            GermanState::Brandenburg => "BB",
+            GermanState::Bremen => "HB",
+            GermanState::Hamburg => "HH",
+            GermanState::Hessen => "HE",
+            GermanState::MecklenburgVorpommern => "MV",
+            GermanState::Niedersachsen => "NI",
+            GermanState::NordrheinWestfalen => "NW",
+            GermanState::RheinlandPfalz => "RP",
+            GermanState::Saarland => "SL",
+            GermanState::Sachsen => "SN",
+            GermanState::SachsenAnhalt => "ST",
+            GermanState::SchleswigHolstein => "SH",
+            GermanState::Thueringen => "TH",
+        }
+    }
+}
+
+/// Region whose public holidays apply, given as ISO 3166 code like `DE` for
+/// the nationwide holidays of Germany or `DE-BY` for the holidays of Bavaria.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(try_from = "String")]
+pub enum Region {
+    Germany(Option<GermanState>),
+}
+
+impl Region {
+    /// Public holidays of the region in the given year, ordered by date.
+    pub fn holidays(&self, year: i32) -> Vec<(NaiveDate, &'static str)> {
+        match self {
+            Region::Germany(state) => german_holidays(year, *state),
+        }
+    }
+}
+
+impl FromStr for Region {
+    type Err = HolidayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || HolidayError::UnknownRegion(s.to_owned());
+        let (country, state) = match s.split_once('-') {
+            Some((country, state)) => (country, Some(state)),
+            None => (s, None),
+        };
+        if !country.eq_ignore_ascii_case("DE") {
+            return Err(unknown());
+        }
+        let state = state
+            .map(|state| {
+                GermanState::ALL
+                    .into_iter()
+                    .find(|candidate| candidate.code().eq_ignore_ascii_case(state))
+                    .ok_or_else(unknown)
+            })
+            .transpose()?;
+        Ok(Region::Germany(state))
+    }
+}
+
+impl TryFrom<String> for Region {
+    type Error = HolidayError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Region::Germany(None) => f.write_str("DE"),
+            Region::Germany(Some(state)) => write!(f, "DE-{}", state.code()),
+        }
+    }
+}
+
+/// Public holidays without working time, e.g. configured in the
+/// `[schedule.holidays]` table of the config file.
+/// - `region` selects the built-in holidays, e.g. `DE-NW`.
+/// - `extra` adds further days off by date, e.g. company holidays like
+///   `{ 2022-12-24 = "Heiligabend" }`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Holidays {
+    pub region: Option<Region>,
+    pub extra: BTreeMap<NaiveDate, String>,
+}
+
+impl Holidays {
+    /// Name of the holiday on the given date, if it is one. Extra dates take
+    /// precedence over the holidays of the region.
+    pub fn holiday(&self, date: NaiveDate) -> Option<String> {
+        if let Some(name) = self.extra.get(&date) {
+            return Some(name.clone());
+        }
+        self.region?
+            .holidays(date.year())
+            .into_iter()
+            .find(|(holiday, _)| *holiday == date)
+            .map(|(_, name)| name.to_owned())
+    }
+}
+
+/// Date of Easter Sunday in the Gregorian calendar (anonymous Gregorian
+/// algorithm).
+pub fn easter_sunday(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).expect("valid Easter date")
+}
+
+/// Public holidays of Germany, including those of the given state. Holidays
+/// only observed in some municipalities of a state are not included.
+fn german_holidays(year: i32, state: Option<GermanState>) -> Vec<(NaiveDate, &'static str)> {
+    use GermanState::*;
+
+    let date = |month, day| NaiveDate::from_ymd_opt(year, month, day).expect("valid date");
+    let easter = easter_sunday(year);
+    let in_state = |states: &[GermanState]| state.is_some_and(|state| states.contains(&state));
+
+    let mut holidays = vec![
+        (date(1, 1), "Neujahr"),
+        (easter - Duration::days(2), "Karfreitag"),
+        (easter + Duration::days(1), "Ostermontag"),
+        (date(5, 1), "Tag der Arbeit"),
+        (easter + Duration::days(39), "Christi Himmelfahrt"),
+        (easter + Duration::days(50), "Pfingstmontag"),
+        (date(10, 3), "Tag der Deutschen Einheit"),
+        (date(12, 25), "1. Weihnachtstag"),
+        (date(12, 26), "2. Weihnachtstag"),
+    ];
+    if in_state(&[BadenWuerttemberg, Bayern, SachsenAnhalt]) {
+        holidays.push((date(1, 6), "Heilige Drei Könige"));
+    }
+    if (year >= 2019 && in_state(&[Berlin])) || (year >= 2023 && in_state(&[MecklenburgVorpommern]))
+    {
+        holidays.push((date(3, 8), "Internationaler Frauentag"));
+    }
+    if in_state(&[Brandenburg]) {
+        holidays.push((easter, "Ostersonntag"));
+        holidays.push((easter + Duration::days(49), "Pfingstsonntag"));
+    }
+    if in_state(&[
+        BadenWuerttemberg,
+        Bayern,
+        Hessen,
+        NordrheinWestfalen,
+        RheinlandPfalz,
+        Saarland,
+    ]) {
+        holidays.push((easter + Duration::days(60), "Fronleichnam"));
+    }
+    if in_state(&[Saarland]) {
+        holidays.push((date(8, 15), "Mariä Himmelfahrt"));
+    }
+    if year >= 2019 && in_state(&[Thueringen]) {
+        holidays.push((date(9, 20), "Weltkindertag"));
+    }
+    // Reformation Day was a nationwide holiday for its 500th anniversary.
+    if year == 2017
+        || in_state(&[
+            Brandenburg,
+            MecklenburgVorpommern,
+            Sachsen,
+            SachsenAnhalt,
+            Thueringen,
+        ])
+        || (year >= 2018 && in_state(&[Bremen, Hamburg, Niedersachsen, SchleswigHolstein]))
+    {
+        holidays.push((date(10, 31), "Reformationstag"));
+    }
+    if in_state(&[
+        BadenWuerttemberg,
+        Bayern,
+        NordrheinWestfalen,
+        RheinlandPfalz,
+        Saarland,
+    ]) {
+        holidays.push((date(11, 1), "Allerheiligen"));
+    }
+    if in_state(&[Sachsen]) {
+        // Wednesday before November 23.
+        let repentance_day = (16..=22)
+            .map(|day| date(11, day))
+            .find(|date| date.weekday() == Weekday::Wed)
+            .expect("one Wednesday in seven days");
+        holidays.push((repentance_day, "Buß- und Bettag"));
+    }
+    holidays.sort();
+    holidays
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_easter_sunday() {
+        assert_eq!(easter_sunday(2022), date(2022, 4, 17));
+        assert_eq!(easter_sunday(2023), date(2023, 4, 9));
+        assert_eq!(easter_sunday(2024), date(2024, 3, 31));
+        assert_eq!(easter_sunday(2038), date(2038, 4, 25));
+    }
+
+    #[test]
+    fn test_german_holidays() {
+        let nationwide: Region = "DE".parse().unwrap();
+        assert_eq!(nationwide.holidays(2022).len(), 9);
+        assert!(nationwide
+            .holidays(2022)
+            .contains(&(date(2022, 5, 26), "Christi Himmelfahrt")));
+
+        let bavaria: Region = "de-by".parse().unwrap();
+        assert_eq!(bavaria, Region::Germany(Some(GermanState::Bayern)));
+        assert_eq!(bavaria.to_string(), "DE-BY");
+        assert!(bavaria
+            .holidays(2022)
+            .contains(&(date(2022, 6, 16), "Fronleichnam")));
+
+        let saxony: Region = "DE-SN".parse().unwrap();
+        assert!(saxony
+            .holidays(2022)
+            .contains(&(date(2022, 11, 16), "Buß- und Bettag")));
+        assert!(saxony
+            .holidays(2023)
+            .contains(&(date(2023, 11, 22), "Buß- und Bettag")));
+
+        let hamburg: Region = "DE-HH".parse().unwrap();
+        assert!(!hamburg
+            .holidays(2016)
+            .iter()
+            .any(|(date, _)| date.month() == 10 && date.day() == 31));
+        assert!(hamburg
+            .holidays(2018)
+            .contains(&(date(2018, 10, 31), "Reformationstag")));
+
+        assert!("DE-XX".parse::<Region>().is_err());
+        assert!("AT".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn test_holidays_with_extra_dates() {
+        let holidays: Holidays = toml::from_str(
+            r#"
+            region = "DE-NW"
+            extra = { 2022-12-24 = "Heiligabend", "2022-11-01" = "Betriebsausflug" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            holidays.holiday(date(2022, 10, 3)),
+            Some("Tag der Deutschen Einheit".to_string())
+        );
+        assert_eq!(
+            holidays.holiday(date(2022, 12, 24)),
+            Some("Heiligabend".to_string())
+        );
+        assert_eq!(
+            holidays.holiday(date(2022, 11, 1)),
+            Some("Betriebsausflug".to_string())
+        );
+        assert_eq!(holidays.holiday(date(2022, 10, 4)), None);
+        assert_eq!(Holidays::default().holiday(date(2022, 10, 3)), None);
+    }
+}
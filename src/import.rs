@@ -0,0 +1,457 @@
+use crate::{
+    clockify::TimeInterval,
+    source::{Entry, Query, SourceError, TimeSource},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone};
+use serde::Deserialize;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Date formats used by Clockify exports depending on the user settings.
+const DATE_FORMATS: [&str; 4] = ["%m/%d/%Y", "%d/%m/%Y", "%d.%m.%Y", "%Y-%m-%d"];
+/// Time formats used by Clockify exports depending on the user settings.
+const TIME_FORMATS: [&str; 4] = ["%H:%M:%S", "%I:%M:%S %p", "%H:%M", "%I:%M %p"];
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+    #[error("CSV reader error")]
+    Csv(#[from] csv::Error),
+    #[error("JSON deserialization error")]
+    Deserialization(#[from] serde_json::Error),
+    #[error("Unknown export format of {0} (expected .csv or .json)")]
+    UnknownFormat(PathBuf),
+    #[error("Missing column '{0}' in header")]
+    MissingColumn(&'static str),
+    #[error("Unsupported date format '{0}'")]
+    InvalidDate(String),
+    #[error(
+        "Ambiguous date '{0}' matches day-first and month-first formats (use --import-date-format)"
+    )]
+    AmbiguousDate(String),
+    #[error("Unsupported time format '{0}'")]
+    InvalidTime(String),
+}
+
+/// Time source importing time entries from a Clockify detailed report export
+/// file. The format is selected by the file extension (`.csv` or `.json`).
+#[derive(Clone, Debug)]
+pub struct ClockifyExportSource {
+    pub path: PathBuf,
+    /// Date format of CSV exports like `%d/%m/%Y`. Detected from the values
+    /// of the file, if not given.
+    pub date_format: Option<String>,
+}
+
+#[async_trait]
+impl TimeSource for ClockifyExportSource {
+    async fn retrieve(&self, query: &Query) -> Result<Vec<Entry>, SourceError> {
+        Ok(import_time_entries(
+            &self.path,
+            query,
+            self.date_format.as_deref(),
+        )?)
+    }
+}
+
+/// Import the queried time entries from a Clockify detailed report export
+/// file, ordered by start time. The date format only applies to CSV exports
+/// (see `read_clockify_csv`).
+pub fn import_time_entries(
+    path: &Path,
+    query: &Query,
+    date_format: Option<&str>,
+) -> Result<Vec<Entry>, ImportError> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let file = fs::File::open(path)?;
+    let mut entries = match extension.as_deref() {
+        Some("csv") => read_clockify_csv(file, date_format)?,
+        Some("json") => read_clockify_json(file)?,
+        _ => return Err(ImportError::UnknownFormat(path.to_owned())),
+    };
+    entries.retain(|entry| query.matches(entry));
+    entries.sort_by_key(|entry| entry.start);
+    Ok(entries)
+}
+
+/// Read time entries from a Clockify detailed report exported as CSV.
+/// The date and time formats depend on the settings of the exporting user, so
+/// the first supported format matching all values of the file is used, unless
+/// a date format is given. Dates which are valid both day-first and
+/// month-first, like `02/10/2022`, are rejected as ambiguous.
+pub fn read_clockify_csv<R: io::Read>(
+    rdr: R,
+    date_format: Option<&str>,
+) -> Result<Vec<Entry>, ImportError> {
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let headers = rdr.headers()?.clone();
+    let column = |name: &'static str| {
+        headers
+            .iter()
+            .position(|header| header.trim_start_matches('\u{feff}') == name)
+            .ok_or(ImportError::MissingColumn(name))
+    };
+    let project_column = column("Project").ok();
+    let client_column = column("Client").ok();
+    let tags_column = column("Tags").ok();
+    let description_column = column("Description")?;
+    let task_column = column("Task")?;
+    let billable_column = column("Billable")?;
+    let start_date_column = column("Start Date")?;
+    let start_time_column = column("Start Time")?;
+    let end_date_column = column("End Date")?;
+    let end_time_column = column("End Time")?;
+
+    let records = rdr.records().collect::<Result<Vec<_>, _>>()?;
+    fn field(record: &csv::StringRecord, index: usize) -> &str {
+        record.get(index).unwrap_or_default().trim()
+    }
+    let date_format = match date_format {
+        Some(date_format) => date_format,
+        None => detect_date_format(records.iter().flat_map(|record| {
+            [
+                field(record, start_date_column),
+                field(record, end_date_column),
+            ]
+        }))?,
+    };
+    let time_format = detect_format(
+        &TIME_FORMATS,
+        records.iter().flat_map(|record| {
+            [
+                field(record, start_time_column),
+                field(record, end_time_column),
+            ]
+        }),
+        |value, format| NaiveTime::parse_from_str(value, format).is_ok(),
+    )
+    .map_err(ImportError::InvalidTime)?;
+    let parse = |date: &str, time: &str| -> Result<DateTime<Local>, ImportError> {
+        let date = NaiveDate::parse_from_str(date, date_format)
+            .map_err(|_| ImportError::InvalidDate(date.to_owned()))?;
+        let time = NaiveTime::parse_from_str(time, time_format)
+            .map_err(|_| ImportError::InvalidTime(time.to_owned()))?;
+        Local
+            .from_local_datetime(&date.and_time(time))
+            .earliest()
+            .ok_or_else(|| ImportError::InvalidTime(time.to_string()))
+    };
+
+    records
+        .iter()
+        .map(|record| {
+            let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_owned());
+            Ok(Entry {
+                description: field(record, description_column).to_owned(),
+                project: project_column.and_then(|column| non_empty(field(record, column))),
+                client: client_column.and_then(|column| non_empty(field(record, column))),
+                task: non_empty(field(record, task_column)),
+                tags: tags_column
+                    .map(|column| split_tags(field(record, column)))
+                    .unwrap_or_default(),
+                billable: field(record, billable_column).eq_ignore_ascii_case("yes"),
+                start: parse(
+                    field(record, start_date_column),
+                    field(record, start_time_column),
+                )?,
+                end: parse(
+                    field(record, end_date_column),
+                    field(record, end_time_column),
+                )?,
+            })
+        })
+        .collect()
+}
+
+/// Time entry of a Clockify detailed report exported as JSON.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedTimeEntry {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    billable: bool,
+    project_name: Option<String>,
+    client_name: Option<String>,
+    task_name: Option<String>,
+    #[serde(default)]
+    tags: Option<Vec<ExportedTag>>,
+    time_interval: TimeInterval,
+}
+
+/// Tag of a time entry of a Clockify detailed report exported as JSON.
+#[derive(Debug, Deserialize)]
+struct ExportedTag {
+    name: String,
+}
+
+/// Clockify detailed report exported as JSON, either as report object or as
+/// plain list of time entries.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ExportedReport {
+    Report { timeentries: Vec<ExportedTimeEntry> },
+    TimeEntries(Vec<ExportedTimeEntry>),
+}
+
+/// Read time entries from a Clockify detailed report exported as JSON.
+pub fn read_clockify_json<R: io::Read>(rdr: R) -> Result<Vec<Entry>, ImportError> {
+    let report: ExportedReport = serde_json::from_reader(rdr)?;
+    let time_entries = match report {
+        ExportedReport::Report { timeentries } => timeentries,
+        ExportedReport::TimeEntries(time_entries) => time_entries,
+    };
+    Ok(time_entries
+        .into_iter()
+        .map(|entry| Entry {
+            description: entry.description,
+            project: entry.project_name.filter(|name| !name.is_empty()),
+            client: entry.client_name.filter(|name| !name.is_empty()),
+            task: entry.task_name.filter(|name| !name.is_empty()),
+            tags: entry
+                .tags
+                .unwrap_or_default()
+                .into_iter()
+                .map(|tag| tag.name)
+                .collect(),
+            billable: entry.billable,
+            start: entry.time_interval.start,
+            end: entry.time_interval.end,
+        })
+        .collect())
+}
+
+/// Split the comma separated tags of a CSV export.
+fn split_tags(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Find the first date format for which all values are valid. Fails if another
+/// format is valid for all values as well, but reads any of them as a
+/// different date.
+fn detect_date_format<'a>(
+    values: impl Iterator<Item = &'a str> + Clone,
+) -> Result<&'static str, ImportError> {
+    let parse = |value: &str, format: &str| NaiveDate::parse_from_str(value, format).ok();
+    let format = detect_format(&DATE_FORMATS, values.clone(), |value, format| {
+        parse(value, format).is_some()
+    })
+    .map_err(ImportError::InvalidDate)?;
+    for other in DATE_FORMATS.iter().filter(|other| **other != format) {
+        if !values.clone().all(|value| parse(value, other).is_some()) {
+            continue;
+        }
+        if let Some(value) = values
+            .clone()
+            .find(|value| parse(value, format) != parse(value, other))
+        {
+            return Err(ImportError::AmbiguousDate(value.to_owned()));
+        }
+    }
+    Ok(format)
+}
+
+/// Find the first format for which all values are valid.
+/// Returns the first invalid value, if no format matches.
+fn detect_format<'a>(
+    formats: &[&'static str],
+    values: impl Iterator<Item = &'a str> + Clone,
+    is_valid: impl Fn(&str, &str) -> bool,
+) -> Result<&'static str, String> {
+    formats
+        .iter()
+        .find(|format| values.clone().all(|value| is_valid(value, format)))
+        .copied()
+        .ok_or_else(|| {
+            values
+                .clone()
+                .find(|value| !formats.iter().any(|format| is_valid(value, format)))
+                .unwrap_or_default()
+                .to_owned()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_clockify_csv() {
+        let csv = "\u{feff}\"Project\",\"Client\",\"Description\",\"Task\",\"User\",\"Group\",\
+                   \"Email\",\"Tags\",\"Billable\",\"Start Date\",\"Start Time\",\"End Date\",\
+                   \"End Time\",\"Duration (h)\",\"Duration (decimal)\"\n\
+                   \"Project\",\"Client\",\"Entry 1\",\"Task 1\",\"User\",\"\",\"user@example.com\",\
+                   \"meeting, travel\",\"Yes\",\"10/13/2022\",\"08:00:00 AM\",\"10/13/2022\",\
+                   \"09:30:15 AM\",\"01:30:15\",\"1.50\"\n\
+                   \"Project\",\"Client\",\"Entry 2\",\"\",\"User\",\"\",\"user@example.com\",\
+                   \"\",\"No\",\"10/12/2022\",\"11:00:00 PM\",\"10/13/2022\",\"12:15:00 AM\",\
+                   \"01:15:00\",\"1.25\"\n";
+        let expected_result = vec![
+            Entry {
+                description: "Entry 1".to_string(),
+                project: Some("Project".to_string()),
+                client: Some("Client".to_string()),
+                task: Some("Task 1".to_string()),
+                tags: vec!["meeting".to_string(), "travel".to_string()],
+                billable: true,
+                start: Local.with_ymd_and_hms(2022, 10, 13, 8, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 13, 9, 30, 15).unwrap(),
+            },
+            Entry {
+                description: "Entry 2".to_string(),
+                project: Some("Project".to_string()),
+                client: Some("Client".to_string()),
+                task: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 12, 23, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 13, 0, 15, 0).unwrap(),
+            },
+        ];
+        let result = read_clockify_csv(csv.as_bytes(), None).unwrap();
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn test_read_clockify_csv_detects_day_first_dates() {
+        let csv = "Description,Task,Billable,Start Date,Start Time,End Date,End Time\n\
+                   Entry 1,,No,02/10/2022,08:00:00,02/10/2022,09:00:00\n\
+                   Entry 2,,No,13/10/2022,08:00:00,13/10/2022,09:00:00\n";
+        let result = read_clockify_csv(csv.as_bytes(), None).unwrap();
+        assert_eq!(
+            result[0].start,
+            Local.with_ymd_and_hms(2022, 10, 2, 8, 0, 0).unwrap()
+        );
+        assert_eq!(result[0].project, None);
+    }
+
+    #[test]
+    fn test_read_clockify_csv_rejects_ambiguous_dates() {
+        let csv = "Description,Task,Billable,Start Date,Start Time,End Date,End Time\n\
+                   Entry 1,,No,02/10/2022,08:00:00,02/10/2022,09:00:00\n\
+                   Entry 2,,No,12/10/2022,08:00:00,12/10/2022,09:00:00\n";
+        let result = read_clockify_csv(csv.as_bytes(), None);
+        assert!(matches!(result, Err(ImportError::AmbiguousDate(value)) if value == "02/10/2022"));
+
+        let result = read_clockify_csv(csv.as_bytes(), Some("%d/%m/%Y")).unwrap();
+        assert_eq!(
+            result[1].start,
+            Local.with_ymd_and_hms(2022, 10, 12, 8, 0, 0).unwrap()
+        );
+
+        // Dates with equal day and month are read the same way by both.
+        let csv = "Description,Task,Billable,Start Date,Start Time,End Date,End Time\n\
+                   Entry 1,,No,10/10/2022,08:00:00,10/10/2022,09:00:00\n";
+        read_clockify_csv(csv.as_bytes(), None).unwrap();
+    }
+
+    #[test]
+    fn test_read_clockify_csv_errors() {
+        let csv = "Description,Task,Billable,Start Date,Start Time,End Date\n";
+        let result = read_clockify_csv(csv.as_bytes(), None);
+        assert!(matches!(
+            result,
+            Err(ImportError::MissingColumn("End Time"))
+        ));
+
+        let csv = "Description,Task,Billable,Start Date,Start Time,End Date,End Time\n\
+                   Entry 1,,No,2022/10/02,08:00:00,02.10.2022,09:00:00\n";
+        let result = read_clockify_csv(csv.as_bytes(), None);
+        assert!(matches!(result, Err(ImportError::InvalidDate(value)) if value == "2022/10/02"));
+    }
+
+    #[test]
+    fn test_read_clockify_json() {
+        let json = r#"{
+            "totals": [],
+            "timeentries": [
+                {
+                    "_id": "1",
+                    "description": "Entry 1",
+                    "billable": true,
+                    "taskId": "abcdef",
+                    "taskName": "Task 1",
+                    "projectName": "Project",
+                    "clientName": "Client",
+                    "tags": [{ "_id": "tag1", "name": "meeting" }],
+                    "timeInterval": {
+                        "start": "2022-10-13T08:00:00+02:00",
+                        "end": "2022-10-13T09:30:00+02:00",
+                        "duration": 5400
+                    }
+                },
+                {
+                    "_id": "2",
+                    "description": "Entry 2",
+                    "billable": false,
+                    "taskId": null,
+                    "timeInterval": {
+                        "start": "2022-10-12T08:00:00Z",
+                        "end": "2022-10-12T09:00:00Z",
+                        "duration": 3600
+                    }
+                }
+            ]
+        }"#;
+        let result = read_clockify_json(json.as_bytes()).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].task, Some("Task 1".to_string()));
+        assert_eq!(result[0].project, Some("Project".to_string()));
+        assert_eq!(result[0].client, Some("Client".to_string()));
+        assert_eq!(result[0].tags, vec!["meeting".to_string()]);
+        assert_eq!(
+            result[0].start,
+            DateTime::parse_from_rfc3339("2022-10-13T08:00:00+02:00").unwrap()
+        );
+        assert_eq!(result[1].description, "Entry 2");
+        assert!(!result[1].billable);
+        assert_eq!(result[1].task, None);
+
+        // Plain list of time entries.
+        let json = r#"[{
+            "description": "Entry 1",
+            "timeInterval": {
+                "start": "2022-10-13T08:00:00Z",
+                "end": "2022-10-13T09:30:00Z"
+            }
+        }]"#;
+        let result = read_clockify_json(json.as_bytes()).unwrap();
+        assert_eq!(result.len(), 1);
+    }
+
+    #[test]
+    fn test_import_time_entries_filters_query_oldest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        fs::write(
+            &path,
+            "Description,Task,Billable,Start Date,Start Time,End Date,End Time\n\
+             Entry 1,,No,2022-09-30,08:00,2022-09-30,09:00\n\
+             Entry 3,,No,2022-10-02,08:00,2022-10-02,09:00\n\
+             Entry 2,,No,2022-10-01,08:00,2022-10-01,09:00\n",
+        )
+        .unwrap();
+        let query = Query::month(2022, 10).unwrap();
+        let result = import_time_entries(&path, &query, None).unwrap();
+        let descriptions: Vec<_> = result.iter().map(|entry| &entry.description).collect();
+        assert_eq!(descriptions, vec!["Entry 2", "Entry 3"]);
+
+        let path = dir.path().join("export.xlsx");
+        fs::write(&path, "").unwrap();
+        let result = import_time_entries(&path, &query, None);
+        assert!(matches!(result, Err(ImportError::UnknownFormat(_))));
+    }
+}
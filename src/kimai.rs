@@ -0,0 +1,279 @@
+use crate::source::{Entry, Query, SourceError, TimeSource};
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, Local};
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Number of timesheets requested per page.
+const PAGE_SIZE: u32 = 50;
+
+#[derive(Debug, Error)]
+pub enum KimaiError {
+    #[error("REST API error")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("JSON deserialization error")]
+    Deserialization(#[from] serde_json::Error),
+}
+
+/// Time source retrieving the timesheets of the API user from a (self-hosted)
+/// Kimai instance. Configured in the `[kimai]` table of the config file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct KimaiSource {
+    /// Base URL of the Kimai instance, e.g. `https://kimai.example.com`.
+    pub base_url: String,
+    pub api_token: String,
+    /// Only retrieve timesheets of this project, if given.
+    #[serde(default)]
+    pub project_id: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Customer {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Project {
+    pub name: String,
+    pub customer: Option<Customer>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Activity {
+    pub name: String,
+}
+
+/// Timesheet record as returned by `GET /api/timesheets?full=true`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Timesheet {
+    #[serde(deserialize_with = "deserialize_time")]
+    pub begin: DateTime<Local>,
+    /// End of the timesheet, missing for a running timer.
+    #[serde(default, deserialize_with = "deserialize_optional_time")]
+    pub end: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub billable: bool,
+    pub project: Option<Project>,
+    pub activity: Option<Activity>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[async_trait]
+impl TimeSource for KimaiSource {
+    async fn retrieve(&self, query: &Query) -> Result<Vec<Entry>, SourceError> {
+        let mut entries = self
+            .retrieve_timesheets(query)
+            .await?
+            .into_iter()
+            .filter_map(into_entry)
+            .collect::<Vec<_>>();
+        entries.retain(|entry| query.matches(entry));
+        entries.sort_by_key(|entry| entry.start);
+        Ok(entries)
+    }
+}
+
+impl KimaiSource {
+    /// Retrieve all timesheets of the queried days, following the pagination
+    /// announced through the `X-Total-Pages` header.
+    pub async fn retrieve_timesheets(&self, query: &Query) -> Result<Vec<Timesheet>, KimaiError> {
+        let client = reqwest::ClientBuilder::new()
+            .user_agent("clockify-to-time-sheet")
+            .build()?;
+        // Kimai expects local date times without offset (HTML5 format).
+        let begin = format!("{}T00:00:00", query.start.format("%Y-%m-%d"));
+        let end = format!("{}T00:00:00", query.end.format("%Y-%m-%d"));
+        let mut timesheets: Vec<Timesheet> = vec![];
+        let mut page = 1;
+        loop {
+            let mut request = client
+                .get(format!(
+                    "{}/api/timesheets",
+                    self.base_url.trim_end_matches('/')
+                ))
+                .bearer_auth(&self.api_token)
+                .query(&[
+                    ("full", "true".to_string()),
+                    ("begin", begin.clone()),
+                    ("end", end.clone()),
+                    ("size", PAGE_SIZE.to_string()),
+                    ("page", page.to_string()),
+                ]);
+            if let Some(project_id) = self.project_id {
+                request = request.query(&[("project", project_id)]);
+            }
+            if let Some(billable) = query.billable {
+                request = request.query(&[("billable", u8::from(billable))]);
+            }
+            let response = request.send().await?.error_for_status()?;
+            let total_pages = response
+                .headers()
+                .get("X-Total-Pages")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u32>().ok())
+                .unwrap_or(1);
+            let response_body = response.text().await?;
+            timesheets.extend(serde_json::from_str::<Vec<Timesheet>>(&response_body)?);
+            if page >= total_pages {
+                break;
+            }
+            page += 1;
+        }
+        Ok(timesheets)
+    }
+}
+
+/// Convert a finished Kimai timesheet into a normalized entry. The activity is
+/// used as the task.
+fn into_entry(timesheet: Timesheet) -> Option<Entry> {
+    Some(Entry {
+        description: timesheet.description.unwrap_or_default(),
+        client: timesheet
+            .project
+            .as_ref()
+            .and_then(|project| project.customer.as_ref())
+            .map(|customer| customer.name.clone()),
+        project: timesheet.project.map(|project| project.name),
+        task: timesheet.activity.map(|activity| activity.name),
+        tags: timesheet.tags,
+        billable: timesheet.billable,
+        start: timesheet.begin,
+        end: timesheet.end?,
+    })
+}
+
+/// Parse a Kimai timestamp like `2022-10-01T08:00:00+0200`.
+fn parse_time(value: &str) -> Result<DateTime<Local>, chrono::ParseError> {
+    DateTime::<FixedOffset>::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%z")
+        .map(|time| time.with_timezone(&Local))
+}
+
+fn deserialize_time<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<DateTime<Local>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_time(&value).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_optional_time<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Local>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_time(&value).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn timesheet(id: u32, begin: &str, end: Option<&str>) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "begin": begin,
+            "end": end,
+            "description": format!("Timesheet {id}"),
+            "billable": true,
+            "project": { "id": 1, "name": "Project", "customer": { "id": 1, "name": "Customer" } },
+            "activity": { "id": 1, "name": "Development" },
+            "tags": ["meeting"]
+        })
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(
+            parse_time("2022-10-01T08:00:00+0200").unwrap(),
+            "2022-10-01T06:00:00Z".parse::<DateTime<Local>>().unwrap()
+        );
+        assert_eq!(
+            parse_time("2022-10-01T08:00:00+02:00").unwrap(),
+            "2022-10-01T06:00:00Z".parse::<DateTime<Local>>().unwrap()
+        );
+        assert!(parse_time("2022-10-01 08:00").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_paginated_timesheets() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/timesheets"))
+            .and(header("Authorization", "Bearer token"))
+            .and(query_param("begin", "2022-10-01T00:00:00"))
+            .and(query_param("end", "2022-11-01T00:00:00"))
+            .and(query_param("project", "1"))
+            .and(query_param("billable", "1"))
+            .and(query_param("page", "1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("X-Total-Pages", "2")
+                    .set_body_json(serde_json::json!([
+                        timesheet(3, "2022-10-02T08:00:00+0200", None),
+                        timesheet(
+                            2,
+                            "2022-10-02T07:00:00+0200",
+                            Some("2022-10-02T08:00:00+0200")
+                        ),
+                    ])),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/timesheets"))
+            .and(query_param("page", "2"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("X-Total-Pages", "2")
+                    .set_body_json(serde_json::json!([timesheet(
+                        1,
+                        "2022-10-01T08:00:00+0200",
+                        Some("2022-10-01T09:30:00+0200")
+                    )])),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let source = KimaiSource {
+            base_url: format!("{}/", server.uri()),
+            api_token: "token".to_string(),
+            project_id: Some(1),
+        };
+        let query = Query {
+            billable: Some(true),
+            ..Query::month(2022, 10).unwrap()
+        };
+        let result = source.retrieve(&query).await.unwrap();
+        let expected_result = vec![
+            Entry {
+                description: "Timesheet 1".to_string(),
+                project: Some("Project".to_string()),
+                client: Some("Customer".to_string()),
+                task: Some("Development".to_string()),
+                tags: vec!["meeting".to_string()],
+                billable: true,
+                start: "2022-10-01T06:00:00Z".parse().unwrap(),
+                end: "2022-10-01T07:30:00Z".parse().unwrap(),
+            },
+            Entry {
+                description: "Timesheet 2".to_string(),
+                project: Some("Project".to_string()),
+                client: Some("Customer".to_string()),
+                task: Some("Development".to_string()),
+                tags: vec!["meeting".to_string()],
+                billable: true,
+                start: "2022-10-02T05:00:00Z".parse().unwrap(),
+                end: "2022-10-02T06:00:00Z".parse().unwrap(),
+            },
+        ];
+        assert_eq!(result, expected_result);
+    }
+}
@@ -1,3 +1,21 @@
+pub mod absence;
 pub mod clockify;
+pub mod compliance;
+pub mod diff;
+pub mod filter;
+pub mod harvest;
+pub mod holidays;
+pub mod import;
+pub mod kimai;
+pub mod output;
+pub mod reader;
+pub mod rewrite;
+pub mod schedule;
+pub mod source;
+pub mod summary;
+pub mod template;
+pub mod text_log;
+pub mod timewarrior;
+pub mod toggl;
 pub mod transform;
 pub mod writer;
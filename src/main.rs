@@ -1,58 +1,751 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand, ValueEnum};
 use clockify_to_time_sheet::{
-    clockify::{get_api_user, retrieve_time_entries},
-    transform::transform_time_entries,
-    writer::write_csv,
+    absence::{read_absences, Absence, AbsenceConfig},
+    clockify::{get_api_user, retrieve_time_off, ClockifySource},
+    compliance::{check_working_time_law, insert_required_breaks},
+    diff::diff_time_sheets,
+    filter::{FilterConfig, FilterRules},
+    harvest::HarvestSource,
+    import::ClockifyExportSource,
+    kimai::KimaiSource,
+    output::{check_unique, Output, WriteOptions},
+    reader::read_csv,
+    schedule::{
+        daily_working_time, duration_from_hours, read_balance, write_balance, ScheduleReport,
+        WorkSchedule,
+    },
+    source::{Entry, Query, TimeSource},
+    summary::Summary,
+    template::DescriptionTemplate,
+    text_log::TextLogSource,
+    timewarrior::TimewarriorSource,
+    toggl::TogglSource,
+    transform::{
+        find_overlaps, lay_out_time_sheet_entries, worked_time_sheet_entries, Aggregation,
+        OverlapStrategy, TimeSheetEntry, TransformOptions,
+    },
+    writer::{CsvColumn, CsvWriter, WriterRegistry},
 };
 use serde::Deserialize;
-use std::fs;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
 static CONFIG_FILE: &str = "config.toml";
 
 /// Command line arguments
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    /// Name of CSV output file (default: [YYYY]-[MM].csv)
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Present without subcommand, which requires year and month then
+    #[command(flatten)]
+    generate: Option<GenerateArgs>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compare an existing time sheet with a freshly generated one
+    Diff(DiffArgs),
+    /// Summarize the working time per task, project, tag, week and billable
+    /// flag
+    Summary(SummaryArgs),
+}
+
+/// Arguments for generating the time sheet (default command)
+///
+/// Year and month are members of the `generate` group, which tells whether the
+/// arguments are present, as the derived group of arguments with flattened
+/// ones is left empty.
+#[derive(clap::Args, Debug)]
+#[group(id = "generate")]
+struct GenerateArgs {
+    /// Name of output file or `-` for standard output, may be given multiple
+    /// times (default: [YYYY]-[MM].[EXT] of the output format)
+    #[arg(short, long)]
+    output: Vec<Output>,
+    /// Output format (e.g. csv, ics) for all outputs, inferred from the
+    /// extension of each output file if not given (default: csv)
     #[arg(short, long)]
-    output: Option<String>,
+    format: Option<String>,
+    /// Optional column (billable, tags, target, overtime, balance, holiday) to
+    /// append to CSV outputs, may be given multiple times
+    #[arg(long = "column", value_name = "COLUMN")]
+    columns: Vec<CsvColumn>,
+    /// Overwrite existing output files
+    #[arg(long)]
+    force: bool,
+    /// Keep a timestamped backup of existing output files before replacing
+    /// them
+    #[arg(long)]
+    backup: bool,
+    /// Fail without writing outputs, if the time sheet violates the German
+    /// working time law (ArbZG), instead of printing warnings only
+    #[arg(long)]
+    strict: bool,
+    #[command(flatten)]
+    source: SourceArgs,
+    #[command(flatten)]
+    transform: TransformArgs,
+    /// Year of the time entries to retrieve
+    #[arg(group = "generate")]
+    year: u32,
+    /// Month of the time entries to retrieve
+    #[arg(group = "generate")]
+    month: u32,
+}
+
+/// Arguments for the `diff` command
+#[derive(clap::Args, Debug)]
+struct DiffArgs {
+    #[command(flatten)]
+    source: SourceArgs,
+    #[command(flatten)]
+    transform: TransformArgs,
+    /// Year of the time entries to retrieve
+    year: u32,
+    /// Month of the time entries to retrieve
+    month: u32,
+    /// Existing CSV time sheet (default: [YYYY]-[MM].csv)
+    sheet: Option<PathBuf>,
+}
+
+/// Arguments for the `summary` command
+#[derive(clap::Args, Debug)]
+struct SummaryArgs {
+    #[command(flatten)]
+    source: SourceArgs,
+    /// Output format of the summary
+    #[arg(short, long, value_enum, default_value_t = SummaryFormat::Table)]
+    format: SummaryFormat,
+    /// Summarize an existing CSV time sheet instead of retrieving the time
+    /// entries, which provides no projects
+    #[arg(long, value_name = "FILE", conflicts_with = "import")]
+    sheet: Option<PathBuf>,
     /// Year of the time entries to retrieve
     year: u32,
     /// Month of the time entries to retrieve
     month: u32,
 }
 
-#[derive(Debug, Deserialize)]
+/// Output formats of the summary
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SummaryFormat {
+    /// Plain-text table for the terminal
+    Table,
+    /// CSV with the columns group, name and hours
+    Csv,
+    /// JSON object with the hours per group
+    Json,
+}
+
+/// Arguments selecting where time entries are retrieved from and which of
+/// them are included
+#[derive(clap::Args, Debug)]
+struct SourceArgs {
+    /// Import time entries from a file instead of querying the API, e.g. a
+    /// Clockify detailed report exported as CSV or JSON, a Timewarrior data
+    /// file or directory, or `-` for `timew export` on standard input
+    #[arg(long, value_name = "FILE")]
+    import: Option<PathBuf>,
+    /// Format of the imported file (default: inferred from the file name)
+    #[arg(long, value_enum, requires = "import")]
+    import_format: Option<ImportFormat>,
+    /// Date format of an imported Clockify CSV export, e.g. "%d/%m/%Y"
+    /// (default: detected from the dates of the file)
+    #[arg(long, value_name = "FORMAT", requires = "import")]
+    import_date_format: Option<String>,
+    /// Only include billable time entries
+    #[arg(long, conflicts_with = "non_billable_only")]
+    billable_only: bool,
+    /// Only include non-billable time entries
+    #[arg(long)]
+    non_billable_only: bool,
+    /// Only include time entries with this tag, may be given multiple times
+    /// to include time entries with any of the tags
+    #[arg(long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+    /// Exclude time entries with this tag, may be given multiple times
+    #[arg(long = "exclude-tag", value_name = "TAG")]
+    exclude_tags: Vec<String>,
+    /// Only include time entries of this task, may be given multiple times
+    #[arg(long = "task", value_name = "TASK")]
+    tasks: Vec<String>,
+    /// Exclude time entries of this task, may be given multiple times
+    #[arg(long = "exclude-task", value_name = "TASK")]
+    exclude_tasks: Vec<String>,
+    /// Only include time entries of this project, may be given multiple times
+    #[arg(long = "project", value_name = "PROJECT")]
+    projects: Vec<String>,
+    /// Exclude time entries of this project, may be given multiple times
+    #[arg(long = "exclude-project", value_name = "PROJECT")]
+    exclude_projects: Vec<String>,
+    /// Only include time entries of this client, may be given multiple times
+    #[arg(long = "client", value_name = "CLIENT")]
+    clients: Vec<String>,
+    /// Exclude time entries of this client, may be given multiple times
+    #[arg(long = "exclude-client", value_name = "CLIENT")]
+    exclude_clients: Vec<String>,
+    /// Only include time entries with a description matching this regular
+    /// expression, may be given multiple times
+    #[arg(long = "description", value_name = "REGEX")]
+    descriptions: Vec<String>,
+    /// Exclude time entries with a description matching this regular
+    /// expression, may be given multiple times
+    #[arg(long = "exclude-description", value_name = "REGEX")]
+    exclude_descriptions: Vec<String>,
+}
+
+impl SourceArgs {
+    /// Build the query for the time entries of the given month.
+    fn query(&self, year: u32, month: u32) -> Result<Query> {
+        let query =
+            Query::month(year, month).ok_or_else(|| anyhow!("Invalid month {year}-{month:02}"))?;
+        let billable = match (self.billable_only, self.non_billable_only) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        };
+        Ok(Query {
+            billable,
+            tags: self.tags.clone(),
+            ..query
+        })
+    }
+
+    /// Build the filter rules given on the command line.
+    fn filter(&self) -> FilterConfig {
+        FilterConfig {
+            include: FilterRules {
+                tasks: self.tasks.clone(),
+                tags: vec![],
+                projects: self.projects.clone(),
+                clients: self.clients.clone(),
+                descriptions: self.descriptions.clone(),
+            },
+            exclude: FilterRules {
+                tasks: self.exclude_tasks.clone(),
+                tags: self.exclude_tags.clone(),
+                projects: self.exclude_projects.clone(),
+                clients: self.exclude_clients.clone(),
+                descriptions: self.exclude_descriptions.clone(),
+            },
+        }
+    }
+}
+
+/// Arguments controlling how time entries are turned into time sheet entries
+#[derive(clap::Args, Debug)]
+struct TransformArgs {
+    /// Template for the description of time sheet entries using the
+    /// placeholders {description}, {task}, {project}, {client} and {tags} with
+    /// fallbacks separated by `|`, e.g. "{project} / {task|description}"
+    /// (default: task or description of the time entry)
+    #[arg(long, value_name = "TEMPLATE")]
+    description_template: Option<DescriptionTemplate>,
+    /// How time entries of equal description are combined: `merge`
+    /// subsequent ones or sum them up `per-task-per-day` into back-to-back
+    /// rows (default: merge)
+    #[arg(long, value_name = "MODE")]
+    aggregation: Option<Aggregation>,
+    /// Start time of the rows of each day for the `per-task-per-day`
+    /// aggregation, e.g. 08:00:00 (default: start of the first time entry)
+    #[arg(long, value_name = "TIME")]
+    day_start: Option<NaiveTime>,
+    /// Largest gap in minutes between time entries of equal description which
+    /// are still merged into one row (default: unlimited)
+    #[arg(long, value_name = "MINUTES")]
+    max_merge_gap: Option<u32>,
+    /// Gaps in minutes between merged time entries shorter than this are
+    /// counted as work time instead of break (default: none)
+    #[arg(long, value_name = "MINUTES")]
+    absorb_gap: Option<u32>,
+    /// Add breaks missing for the breaks required by the German working time
+    /// law (ArbZG) to the time sheet entries of each day
+    #[arg(long)]
+    insert_breaks: bool,
+    /// How overlapping time entries are resolved before merging: fail with
+    /// an `error`, `trim-later` or `trim-earlier` entry, or `keep-longest`
+    /// (default: keep both and report the overlaps)
+    #[arg(long, value_name = "STRATEGY")]
+    overlap_strategy: Option<OverlapStrategy>,
+}
+
+impl TransformArgs {
+    /// Override the configured transform options with the command line
+    /// arguments.
+    fn options(&self, config: TransformOptions) -> TransformOptions {
+        TransformOptions {
+            description_template: self
+                .description_template
+                .clone()
+                .or(config.description_template),
+            aggregation: self.aggregation.unwrap_or(config.aggregation),
+            day_start: self.day_start.or(config.day_start),
+            max_merge_gap: self
+                .max_merge_gap
+                .map(|minutes| Duration::minutes(minutes.into()))
+                .or(config.max_merge_gap),
+            absorb_gap: self
+                .absorb_gap
+                .map(|minutes| Duration::minutes(minutes.into()))
+                .or(config.absorb_gap),
+            insert_breaks: self.insert_breaks || config.insert_breaks,
+            overlap_strategy: self.overlap_strategy.or(config.overlap_strategy),
+            ..config
+        }
+    }
+}
+
+/// Formats of files to import time entries from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ImportFormat {
+    /// Clockify detailed report exported as CSV or JSON
+    Clockify,
+    /// Timewarrior data file or directory, or `timew export` JSON
+    Timewarrior,
+    /// Plain-text log with lines like `2022-10-01 08:00-09:30 Description`
+    Text,
+}
+
+impl ImportFormat {
+    /// Infer the format from the name of the imported file. `.data` files,
+    /// directories and standard input are read as Timewarrior data, `.txt` and
+    /// `.log` files as plain-text log and all others as Clockify export.
+    fn infer(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            _ if path == Path::new("-") || path.is_dir() => Self::Timewarrior,
+            Some("data") => Self::Timewarrior,
+            Some("txt" | "log") => Self::Text,
+            _ => Self::Clockify,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
 struct Config {
-    api_key: String,
+    /// Time tracker to retrieve time entries from
+    #[serde(default)]
+    source: SourceKind,
+    api_key: Option<String>,
     // TODO: Project name should be provided via command line argument and ID
     //       should be looked up via the Clockify API.
-    project_id: String,
+    project_id: Option<String>,
+    toggl: Option<TogglSource>,
+    kimai: Option<KimaiSource>,
+    harvest: Option<HarvestSource>,
+    /// Rules selecting the time entries of the time sheet
+    #[serde(default)]
+    filter: FilterConfig,
+    /// Options for transforming time entries into time sheet entries
+    #[serde(default)]
+    transform: TransformOptions,
+    /// Work schedule defining the target working time
+    schedule: Option<WorkSchedule>,
+    /// Sources and rules of absences like vacation and sick leave
+    absences: Option<AbsenceConfig>,
+}
+
+impl Config {
+    /// Read the config file.
+    fn read() -> Result<Self> {
+        Ok(toml::from_str(&fs::read_to_string(CONFIG_FILE)?)?)
+    }
+
+    /// Read the config file, if it exists. Imports work without config file.
+    fn read_optional() -> Result<Self> {
+        if Path::new(CONFIG_FILE).exists() {
+            Self::read()
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SourceKind {
+    #[default]
+    Clockify,
+    Toggl,
+    Kimai,
+    Harvest,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
+    match (args.command, args.generate) {
+        (Some(Command::Diff(diff_args)), _) => diff(diff_args).await,
+        (Some(Command::Summary(summary_args)), _) => summary(summary_args).await,
+        (None, Some(generate_args)) => generate(generate_args).await,
+        (None, None) => Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "year and month are required",
+            )
+            .exit(),
+    }
+}
+
+/// Generate the time sheet for the given month and write it to all outputs.
+async fn generate(args: GenerateArgs) -> Result<()> {
+    let (year, month) = (args.year, args.month);
+    let query = args.source.query(year, month)?;
 
-    let config: Config = toml::from_str(&fs::read_to_string(CONFIG_FILE)?)?;
+    // Resolve output formats and check for existing files first to fail before
+    // querying the API.
+    let mut registry = WriterRegistry::new();
+    registry.register(CsvWriter {
+        columns: args.columns.clone(),
+        schedule: None,
+    });
+    let mut outputs = args.output;
+    if outputs.is_empty() {
+        let writer = registry.select(args.format.as_deref(), None)?;
+        outputs.push(Output::File(PathBuf::from(format!(
+            "{}-{:02}.{}",
+            year,
+            month,
+            writer.extensions()[0]
+        ))));
+    }
+    let write_options = WriteOptions {
+        force: args.force,
+        backup: args.backup,
+    };
+    check_unique(&outputs)?;
+    let outputs = outputs
+        .into_iter()
+        .map(|output| {
+            let writer = registry.select(args.format.as_deref(), output.path())?;
+            output.check(&write_options)?;
+            Ok((output, writer.name().to_owned()))
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-    let api_user = get_api_user(&config.api_key).await?;
+    let (time_sheet_entries, worked_entries) =
+        retrieve_time_sheet_entries(&args.source, &args.transform, &query).await?;
 
-    let time_entries =
-        retrieve_time_entries(&api_user, &config.project_id, args.year, args.month).await?;
-    println!("Retrieved {} time entries.", time_entries.len());
+    let config = Config::read_optional()?;
+    let holidays = config
+        .schedule
+        .as_ref()
+        .map(|schedule| schedule.holidays.clone())
+        .unwrap_or_default();
+    // Check the times the work was done, as rows aggregated per task per day
+    // are laid out back-to-back without breaks.
+    let violations = check_working_time_law(&worked_entries, &holidays);
+    for violation in &violations {
+        eprintln!("Warning: {violation}");
+    }
+    if args.strict && !violations.is_empty() {
+        return Err(anyhow!(
+            "Time sheet violates the working time law on {} occasions",
+            violations.len()
+        ));
+    }
 
-    let time_sheet_entries = transform_time_entries(time_entries);
-    println!(
-        "Writing {} time sheet entries ...",
-        time_sheet_entries.len()
+    // Replace the CSV writer with one including the target working time and
+    // flextime balance according to the work schedule, which requires the
+    // entries.
+    let schedule_report = schedule_report(
+        &config,
+        &query,
+        daily_working_time(
+            time_sheet_entries
+                .iter()
+                .map(|entry| (entry.start, entry.end - entry.start - entry.break_)),
+        ),
+    )
+    .await?;
+    registry.register(CsvWriter {
+        columns: args.columns,
+        schedule: schedule_report.clone(),
+    });
+
+    for (output, writer) in outputs {
+        let writer = registry
+            .get(&writer)
+            .expect("writers of the outputs are registered");
+        eprintln!(
+            "Writing {} time sheet entries to {output} ...",
+            time_sheet_entries.len()
+        );
+        let backup = output.write(&write_options, |wtr| writer.write(wtr, &time_sheet_entries))?;
+        if let Some(backup) = backup {
+            eprintln!("Kept previous file as {}.", backup.display());
+        }
+    }
+
+    if let (Some(schedule), Some(report)) = (&config.schedule, &schedule_report) {
+        let balance = report.closing_balance();
+        eprintln!(
+            "Flextime balance at the end of {year}-{month:02}: {:.2} hours.",
+            balance.num_minutes() as f64 / 60.0
+        );
+        if let Some(state_file) = &schedule.state_file {
+            write_balance(state_file, query.start.year(), query.start.month(), balance)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Compare an existing time sheet with a freshly generated one and print the
+/// differences.
+async fn diff(args: DiffArgs) -> Result<()> {
+    let query = args.source.query(args.year, args.month)?;
+    let sheet = args.sheet.unwrap_or(PathBuf::from(format!(
+        "{}-{:02}.csv",
+        args.year, args.month
+    )));
+    let existing = read_csv(fs::File::open(&sheet)?)?;
+    eprintln!(
+        "Read {} time sheet entries from {}.",
+        existing.len(),
+        sheet.display()
     );
 
-    let file = fs::File::create(
-        args.output
-            .unwrap_or(format!("{}-{:02}.csv", args.year, args.month,)),
-    )?;
-    write_csv(file, &time_sheet_entries)?;
+    let (time_sheet_entries, _) =
+        retrieve_time_sheet_entries(&args.source, &args.transform, &query).await?;
+
+    let sheet_diff = diff_time_sheets(&existing, &time_sheet_entries)?;
+    if sheet_diff.is_empty() {
+        println!("No changes.");
+    } else {
+        println!("{sheet_diff}");
+    }
+
+    Ok(())
+}
+
+/// Summarize the working time of the given month and print the summary.
+async fn summary(args: SummaryArgs) -> Result<()> {
+    let query = args.source.query(args.year, args.month)?;
+    let config = Config::read_optional()?;
+    let (mut summary, working_time) = match args.sheet {
+        Some(sheet) => {
+            let time_sheet_entries = read_csv(fs::File::open(&sheet)?)?;
+            eprintln!(
+                "Read {} time sheet entries from {}.",
+                time_sheet_entries.len(),
+                sheet.display()
+            );
+            (
+                Summary::from_time_sheet_entries(&time_sheet_entries),
+                daily_working_time(
+                    time_sheet_entries
+                        .iter()
+                        .map(|entry| (entry.start, entry.end - entry.start - entry.break_)),
+                ),
+            )
+        }
+        None => {
+            let time_entries = retrieve_time_entries(&args.source, &query, &config).await?;
+            (
+                Summary::from_entries(&time_entries),
+                daily_working_time(
+                    time_entries
+                        .iter()
+                        .map(|entry| (entry.start, entry.end - entry.start)),
+                ),
+            )
+        }
+    };
+    // Without schedule, there are no targets to report.
+    if config.schedule.is_some() {
+        summary.schedule = schedule_report(&config, &query, working_time).await?;
+    }
+
+    let stdout = std::io::stdout().lock();
+    match args.format {
+        SummaryFormat::Table => summary.write_table(stdout)?,
+        SummaryFormat::Csv => summary.write_csv(stdout)?,
+        SummaryFormat::Json => summary.write_json(stdout)?,
+    }
 
     Ok(())
 }
+
+/// Compare the working time per day of the queried period with the work
+/// schedule, if a schedule or absences are configured. The opening balance is
+/// read from the state file, if it contains the balance of the previous month,
+/// or taken from the config otherwise.
+async fn schedule_report(
+    config: &Config,
+    query: &Query,
+    working_time: BTreeMap<NaiveDate, Duration>,
+) -> Result<Option<ScheduleReport>> {
+    if config.schedule.is_none() && config.absences.is_none() {
+        return Ok(None);
+    }
+    let schedule = config.schedule.clone().unwrap_or_default();
+    let stored_balance = match &schedule.state_file {
+        Some(state_file) => read_balance(state_file, query.start.year(), query.start.month())?,
+        None => None,
+    };
+    let opening_balance = stored_balance.unwrap_or_else(|| duration_from_hours(schedule.balance));
+    let absences = retrieve_absences(config, &schedule, query).await?;
+    Ok(Some(
+        ScheduleReport::new(
+            &schedule,
+            query.start,
+            query.end,
+            &working_time,
+            opening_balance,
+        )
+        .with_absences(&absences),
+    ))
+}
+
+/// Retrieve the absence days of the query from the configured absences file
+/// and Clockify time off requests.
+async fn retrieve_absences(
+    config: &Config,
+    schedule: &WorkSchedule,
+    query: &Query,
+) -> Result<Vec<Absence>> {
+    let Some(absence_config) = &config.absences else {
+        return Ok(vec![]);
+    };
+    let mut periods = vec![];
+    if let Some(file) = &absence_config.file {
+        let file_periods = read_absences(fs::File::open(file)?)?;
+        absence_config.check(&file_periods)?;
+        periods.extend(file_periods);
+    }
+    if absence_config.clockify {
+        let Some(api_key) = &config.api_key else {
+            return Err(anyhow!(
+                "Missing `api_key` in {CONFIG_FILE} for Clockify time off"
+            ));
+        };
+        periods.extend(retrieve_time_off(&get_api_user(api_key).await?, query).await?);
+    }
+    let absences = absence_config.resolve(&periods, schedule, query)?;
+    eprintln!("Retrieved {} absence days.", absences.len());
+    Ok(absences)
+}
+
+/// Create the time source selected by the command line arguments, i.e. an
+/// export file or the time tracker configured in the config file.
+fn time_source(source: &SourceArgs) -> Result<Box<dyn TimeSource>> {
+    if let Some(ref path) = source.import {
+        let path = path.clone();
+        return Ok(
+            match source
+                .import_format
+                .unwrap_or_else(|| ImportFormat::infer(&path))
+            {
+                ImportFormat::Clockify => Box::new(ClockifyExportSource {
+                    path,
+                    date_format: source.import_date_format.clone(),
+                }),
+                ImportFormat::Timewarrior => Box::new(TimewarriorSource { path }),
+                ImportFormat::Text => Box::new(TextLogSource { path }),
+            },
+        );
+    }
+    let config = Config::read()?;
+    match config.source {
+        SourceKind::Clockify => {
+            let (Some(api_key), Some(project_id)) = (config.api_key, config.project_id) else {
+                return Err(anyhow!(
+                    "Missing `api_key` or `project_id` in {CONFIG_FILE} for Clockify"
+                ));
+            };
+            Ok(Box::new(ClockifySource {
+                api_key,
+                project_id,
+            }))
+        }
+        SourceKind::Toggl => {
+            let toggl = config
+                .toggl
+                .ok_or_else(|| anyhow!("Missing [toggl] table in {CONFIG_FILE}"))?;
+            Ok(Box::new(toggl))
+        }
+        SourceKind::Kimai => {
+            let kimai = config
+                .kimai
+                .ok_or_else(|| anyhow!("Missing [kimai] table in {CONFIG_FILE}"))?;
+            Ok(Box::new(kimai))
+        }
+        SourceKind::Harvest => {
+            let harvest = config
+                .harvest
+                .ok_or_else(|| anyhow!("Missing [harvest] table in {CONFIG_FILE}"))?;
+            Ok(Box::new(harvest))
+        }
+    }
+}
+
+/// Retrieve the queried time entries from the selected time source and apply
+/// the configured filter.
+async fn retrieve_time_entries(
+    source: &SourceArgs,
+    query: &Query,
+    config: &Config,
+) -> Result<Vec<Entry>> {
+    let time_entries = time_source(source)?.retrieve(query).await?;
+    eprintln!("Retrieved {} time entries.", time_entries.len());
+
+    let mut filter = config.filter.clone();
+    filter.extend(source.filter());
+    let retrieved = time_entries.len();
+    let time_entries = filter.build()?.apply(time_entries);
+    if time_entries.len() < retrieved {
+        eprintln!(
+            "Filtered out {} time entries.",
+            retrieved - time_entries.len()
+        );
+    }
+    Ok(time_entries)
+}
+
+/// Retrieve the queried time entries and transform them into the rows of the
+/// time sheet and the entries at the times the work was done (see
+/// `worked_time_sheet_entries`). Required breaks are inserted into the latter
+/// before laying out the rows. Overlapping time entries and inserted breaks
+/// are reported.
+async fn retrieve_time_sheet_entries(
+    source: &SourceArgs,
+    transform: &TransformArgs,
+    query: &Query,
+) -> Result<(Vec<TimeSheetEntry>, Vec<TimeSheetEntry>)> {
+    let config = Config::read_optional()?;
+    let time_entries = retrieve_time_entries(source, query, &config).await?;
+
+    let options = transform.options(config.transform);
+    let overlaps = find_overlaps(&time_entries);
+    if !overlaps.is_empty() {
+        for overlap in &overlaps {
+            eprintln!("Warning: Overlapping time entries {overlap}");
+        }
+        match options.overlap_strategy {
+            Some(OverlapStrategy::Error) | None => {}
+            Some(strategy) => eprintln!(
+                "Resolved {} overlaps of time entries ({strategy}).",
+                overlaps.len()
+            ),
+        }
+    }
+    let mut worked_entries = worked_time_sheet_entries(time_entries, &options)?;
+    if options.insert_breaks {
+        for adjustment in insert_required_breaks(&mut worked_entries) {
+            eprintln!("{adjustment}");
+        }
+    }
+    let time_sheet_entries = lay_out_time_sheet_entries(worked_entries.clone(), &options);
+    Ok((time_sheet_entries, worked_entries))
+}
@@ -0,0 +1,324 @@
+use crate::writer::WriterError;
+use chrono::Local;
+use std::{
+    convert::Infallible,
+    fmt, fs,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+    process,
+    str::FromStr,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum OutputError {
+    #[error("Output file {0} already exists (use --force to overwrite)")]
+    AlreadyExists(PathBuf),
+    #[error("Output file {0} is given more than once")]
+    Duplicate(PathBuf),
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+    #[error("Writer error")]
+    Writer(#[from] WriterError),
+}
+
+/// Options controlling how existing output files are treated.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct WriteOptions {
+    /// Overwrite existing output files.
+    pub force: bool,
+    /// Keep a timestamped copy of an existing output file before replacing it.
+    /// Implies `force`, as the previous content is not lost.
+    pub backup: bool,
+}
+
+/// Target for writing a time sheet, either a file or the standard output.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Output {
+    Stdout,
+    File(PathBuf),
+}
+
+impl Output {
+    /// Path of the output file, if the output is a file.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Output::Stdout => None,
+            Output::File(path) => Some(path),
+        }
+    }
+
+    /// Check whether writing to the output is allowed with the given options,
+    /// i.e. whether an existing file would be overwritten without permission.
+    pub fn check(&self, options: &WriteOptions) -> Result<(), OutputError> {
+        match self {
+            Output::File(path) if path.exists() && !options.force && !options.backup => {
+                Err(OutputError::AlreadyExists(path.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Write to the output through the given function.
+    /// Files are written to a temporary file in the same directory first, which
+    /// replaces the target file only after it has been written completely.
+    /// This way, a failure while writing never leaves a truncated file behind.
+    /// Returns the path of the backup file, if one has been created.
+    pub fn write<F>(&self, options: &WriteOptions, write: F) -> Result<Option<PathBuf>, OutputError>
+    where
+        F: FnOnce(&mut dyn Write) -> Result<(), WriterError>,
+    {
+        match self {
+            Output::Stdout => {
+                let mut wtr = io::stdout().lock();
+                write(&mut wtr)?;
+                wtr.flush()?;
+                Ok(None)
+            }
+            Output::File(path) => {
+                self.check(options)?;
+                let temp_path = temp_path(path);
+                if let Err(error) = write_file(&temp_path, write) {
+                    // Ignore errors while cleaning up to report the original.
+                    let _ = fs::remove_file(&temp_path);
+                    return Err(error);
+                }
+                let backup_path = if options.backup && path.exists() {
+                    let backup_path = backup_path(path);
+                    fs::copy(path, &backup_path)?;
+                    Some(backup_path)
+                } else {
+                    None
+                };
+                fs::rename(&temp_path, path)?;
+                Ok(backup_path)
+            }
+        }
+    }
+}
+
+/// Check that no output file is given more than once, as the second write
+/// would replace the first one. Paths are compared without `.` components.
+pub fn check_unique(outputs: &[Output]) -> Result<(), OutputError> {
+    let mut paths: Vec<PathBuf> = vec![];
+    for path in outputs.iter().filter_map(Output::path) {
+        let normalized = path
+            .components()
+            .filter(|component| !matches!(component, Component::CurDir))
+            .collect::<PathBuf>();
+        if paths.contains(&normalized) {
+            return Err(OutputError::Duplicate(path.to_path_buf()));
+        }
+        paths.push(normalized);
+    }
+    Ok(())
+}
+
+impl FromStr for Output {
+    type Err = Infallible;
+
+    /// Parse an output from a command line argument, where `-` denotes the
+    /// standard output and everything else a file name.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(if value == "-" {
+            Output::Stdout
+        } else {
+            Output::File(PathBuf::from(value))
+        })
+    }
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Output::Stdout => write!(f, "standard output"),
+            Output::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Create a new file at the given path and write to it through the given
+/// function, making sure the content has reached the disk before returning.
+fn write_file<F>(path: &Path, write: F) -> Result<(), OutputError>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), WriterError>,
+{
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    let mut wtr = io::BufWriter::new(file);
+    write(&mut wtr)?;
+    let file = wtr.into_inner().map_err(|error| error.into_error())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Path of the temporary file used while writing the given file. It is
+/// located in the same directory to allow renaming it atomically.
+fn temp_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{file_name}.{}.tmp", process::id()))
+}
+
+/// Path of a timestamped backup of the given file. If a backup with the same
+/// timestamp already exists, a counter is appended to keep both.
+fn backup_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let timestamp = Local::now().format("%Y%m%d-%H%M%S");
+    let mut backup_path = path.with_file_name(format!("{file_name}.{timestamp}.bak"));
+    let mut counter = 1;
+    while backup_path.exists() {
+        backup_path = path.with_file_name(format!("{file_name}.{timestamp}-{counter}.bak"));
+        counter += 1;
+    }
+    backup_path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_content(content: &str) -> impl FnOnce(&mut dyn Write) -> Result<(), WriterError> + '_ {
+        move |wtr| Ok(wtr.write_all(content.as_bytes())?)
+    }
+
+    #[test]
+    fn test_output_from_str() {
+        assert_eq!("-".parse::<Output>().unwrap(), Output::Stdout);
+        assert_eq!(
+            "2022-10.csv".parse::<Output>().unwrap(),
+            Output::File(PathBuf::from("2022-10.csv"))
+        );
+        assert_eq!(
+            "./-".parse::<Output>().unwrap(),
+            Output::File(PathBuf::from("./-"))
+        );
+    }
+
+    #[test]
+    fn test_output_path() {
+        assert_eq!(Output::Stdout.path(), None);
+        let output = Output::File(PathBuf::from("out/2022-10.ics"));
+        assert_eq!(output.path(), Some(Path::new("out/2022-10.ics")));
+    }
+
+    #[test]
+    fn test_check_unique_outputs() {
+        let csv = Output::File(PathBuf::from("2022-10.csv"));
+        let ics = Output::File(PathBuf::from("2022-10.ics"));
+        check_unique(&[csv.clone(), ics.clone(), Output::Stdout, Output::Stdout]).unwrap();
+        let result = check_unique(&[
+            csv.clone(),
+            ics,
+            Output::File(PathBuf::from("./2022-10.csv")),
+        ]);
+        assert!(
+            matches!(result, Err(OutputError::Duplicate(path)) if path == Path::new("./2022-10.csv"))
+        );
+    }
+
+    #[test]
+    fn test_write_new_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("2022-10.csv");
+        let output = Output::File(path.clone());
+        let backup = output
+            .write(&WriteOptions::default(), write_content("new"))
+            .unwrap();
+        assert_eq!(backup, None);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        // Only the target file is left in the directory.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_refuses_to_overwrite_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("2022-10.csv");
+        fs::write(&path, "old").unwrap();
+        let output = Output::File(path.clone());
+        assert!(matches!(
+            output.check(&WriteOptions::default()),
+            Err(OutputError::AlreadyExists(_))
+        ));
+        let result = output.write(&WriteOptions::default(), write_content("new"));
+        assert!(matches!(result, Err(OutputError::AlreadyExists(_))));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+    }
+
+    #[test]
+    fn test_write_overwrites_existing_file_when_forced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("2022-10.csv");
+        fs::write(&path, "old").unwrap();
+        let output = Output::File(path.clone());
+        let options = WriteOptions {
+            force: true,
+            backup: false,
+        };
+        output.check(&options).unwrap();
+        output.write(&options, write_content("new")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_keeps_backup_of_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("2022-10.csv");
+        fs::write(&path, "old").unwrap();
+        let output = Output::File(path.clone());
+        let options = WriteOptions {
+            force: false,
+            backup: true,
+        };
+        let backup = output.write(&options, write_content("new")).unwrap();
+        let backup = backup.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "old");
+        let backup_name = backup.file_name().unwrap().to_str().unwrap();
+        assert!(backup_name.starts_with("2022-10.csv."));
+        assert!(backup_name.ends_with(".bak"));
+    }
+
+    #[test]
+    fn test_write_keeps_all_backups_within_the_same_second() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("2022-10.csv");
+        fs::write(&path, "first").unwrap();
+        let output = Output::File(path.clone());
+        let options = WriteOptions {
+            force: false,
+            backup: true,
+        };
+        let first = output.write(&options, write_content("second")).unwrap();
+        let second = output.write(&options, write_content("third")).unwrap();
+        let third = output.write(&options, write_content("fourth")).unwrap();
+        let backups = [first.unwrap(), second.unwrap(), third.unwrap()];
+        assert_eq!(fs::read_to_string(&backups[0]).unwrap(), "first");
+        assert_eq!(fs::read_to_string(&backups[1]).unwrap(), "second");
+        assert_eq!(fs::read_to_string(&backups[2]).unwrap(), "third");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn test_write_failure_keeps_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("2022-10.csv");
+        fs::write(&path, "old").unwrap();
+        let output = Output::File(path.clone());
+        let options = WriteOptions {
+            force: true,
+            backup: false,
+        };
+        let result = output.write(&options, |wtr| {
+            wtr.write_all(b"partial")?;
+            Err(io::Error::other("crash").into())
+        });
+        assert!(matches!(result, Err(OutputError::Writer(_))));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        // The temporary file has been removed.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,406 @@
+use crate::transform::TimeSheetEntry;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, TimeZone};
+use std::io;
+use thiserror::Error;
+
+/// Columns required in a CSV time sheet.
+const REQUIRED_COLUMNS: [&str; 5] = ["date", "start", "end", "break", "description"];
+
+#[derive(Debug, Error)]
+pub enum ReaderError {
+    #[error("CSV reader error")]
+    Csv(#[from] csv::Error),
+    #[error("Missing column '{0}' in header")]
+    MissingColumn(&'static str),
+    #[error("Line {line}: Invalid date '{value}'")]
+    InvalidDate { line: u64, value: String },
+    #[error("Line {line}: Invalid time '{value}'")]
+    InvalidTime { line: u64, value: String },
+    #[error("Line {line}: Invalid break '{value}'")]
+    InvalidBreak { line: u64, value: String },
+    #[error("Line {line}: Entry without date")]
+    MissingDate { line: u64 },
+    #[error("Line {line}: Break exceeds the duration of the entry")]
+    BreakExceedsDuration { line: u64 },
+}
+
+/// Read time sheet entries from CSV in the format produced by `write_csv`.
+/// - Columns are identified by the header, additional columns are ignored.
+/// - Rows without date continue the date of the previous row.
+/// - An end time before the start time (or `24:00`) denotes the following day,
+///   as the time sheet only contains the date of the start.
+/// - The task of the entries is not part of the time sheet and left empty.
+/// - Entries are billable, if the optional `billable` column contains `yes`.
+/// - Tags are read from the optional comma separated `tags` column.
+/// - Marker rows of absences without start and end time are skipped.
+pub fn read_csv<R: io::Read>(rdr: R) -> Result<Vec<TimeSheetEntry>, ReaderError> {
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let headers = rdr.headers()?.clone();
+    let mut columns = [0; REQUIRED_COLUMNS.len()];
+    for (column, name) in columns.iter_mut().zip(REQUIRED_COLUMNS) {
+        *column = headers
+            .iter()
+            .position(|header| header.trim() == name)
+            .ok_or(ReaderError::MissingColumn(name))?;
+    }
+    let [date_column, start_column, end_column, break_column, description_column] = columns;
+    let billable_column = headers
+        .iter()
+        .position(|header| header.trim() == "billable");
+    let tags_column = headers.iter().position(|header| header.trim() == "tags");
+
+    let mut result = vec![];
+    let mut last_date: Option<NaiveDate> = None;
+    for record in rdr.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+        let field = |index| record.get(index).unwrap_or_default().trim();
+        let date = if field(date_column).is_empty() {
+            last_date.ok_or(ReaderError::MissingDate { line })?
+        } else {
+            parse_date_field(field(date_column), line)?
+        };
+        last_date = Some(date);
+        if field(start_column).is_empty() && field(end_column).is_empty() {
+            continue;
+        }
+        let start = parse_time_field(date, field(start_column), line)?;
+        let mut end = parse_time_field(date, field(end_column), line)?;
+        if end < start {
+            end = parse_time_field(next_day(date), field(end_column), line)?;
+        }
+        let break_ = parse_break_field(field(break_column), line)?;
+        if break_ > end - start {
+            return Err(ReaderError::BreakExceedsDuration { line });
+        }
+        result.push(TimeSheetEntry {
+            description: record
+                .get(description_column)
+                .unwrap_or_default()
+                .to_string(),
+            task: None,
+            project: None,
+            tags: tags_column
+                .map(|column| {
+                    field(column)
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(str::to_owned)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            billable: billable_column
+                .is_some_and(|column| field(column).eq_ignore_ascii_case("yes")),
+            start,
+            end,
+            break_,
+        });
+    }
+    Ok(result)
+}
+
+/// Parse a date field in dd.mm.yy format.
+fn parse_date_field(value: &str, line: u64) -> Result<NaiveDate, ReaderError> {
+    NaiveDate::parse_from_str(value, "%d.%m.%y").map_err(|_| ReaderError::InvalidDate {
+        line,
+        value: value.to_owned(),
+    })
+}
+
+/// Parse a time field in hh:mm format on the given date. `24:00` denotes the
+/// midnight at the end of the day, which results from rounding up times after
+/// 23:59:30 when writing.
+fn parse_time_field(
+    date: NaiveDate,
+    value: &str,
+    line: u64,
+) -> Result<DateTime<Local>, ReaderError> {
+    let invalid = || ReaderError::InvalidTime {
+        line,
+        value: value.to_owned(),
+    };
+    let (date, time) = if value == "24:00" {
+        (next_day(date), NaiveTime::MIN)
+    } else {
+        (
+            date,
+            NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| invalid())?,
+        )
+    };
+    Local
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .ok_or_else(invalid)
+}
+
+/// Parse a break field in h:mm format. An empty field denotes no break.
+fn parse_break_field(value: &str, line: u64) -> Result<Duration, ReaderError> {
+    if value.is_empty() {
+        return Ok(Duration::zero());
+    }
+    let invalid = || ReaderError::InvalidBreak {
+        line,
+        value: value.to_owned(),
+    };
+    let (hours, minutes) = value.split_once(':').ok_or_else(invalid)?;
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+    if minutes >= 60 {
+        return Err(invalid());
+    }
+    Ok(Duration::minutes(i64::from(hours * 60 + minutes)))
+}
+
+/// Day following the given date. Saturates at the maximum supported date.
+fn next_day(date: NaiveDate) -> NaiveDate {
+    date.succ_opt().unwrap_or(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::write_csv;
+    use chrono::Timelike;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_parse_break_field() {
+        assert_eq!(parse_break_field("", 2).unwrap(), Duration::zero());
+        assert_eq!(parse_break_field("0:01", 2).unwrap(), Duration::minutes(1));
+        assert_eq!(parse_break_field("1:00", 2).unwrap(), Duration::minutes(60));
+        assert_eq!(
+            parse_break_field("12:34", 2).unwrap(),
+            Duration::minutes(754)
+        );
+        assert!(matches!(
+            parse_break_field("1:60", 2),
+            Err(ReaderError::InvalidBreak { line: 2, .. })
+        ));
+        assert!(parse_break_field("90", 2).is_err());
+        assert!(parse_break_field("-1:00", 2).is_err());
+    }
+
+    #[test]
+    fn test_parse_time_field_midnight() {
+        let date = NaiveDate::from_ymd_opt(2022, 10, 1).unwrap();
+        assert_eq!(
+            parse_time_field(date, "24:00", 2).unwrap(),
+            Local.with_ymd_and_hms(2022, 10, 2, 0, 0, 0).unwrap()
+        );
+        assert!(parse_time_field(date, "24:01", 2).is_err());
+    }
+
+    #[test]
+    fn test_reader() {
+        let csv = r#"date,start,end,break,description
+01.10.22,08:00,09:00,,Task 1
+,13:01,15:00,1:01,Task 2
+02.10.22,08:00,09:00,,"Task 3, part 1"
+"#;
+        let expected_result = vec![
+            TimeSheetEntry {
+                description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 8, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 9, 0, 0).unwrap(),
+                break_: Duration::zero(),
+            },
+            TimeSheetEntry {
+                description: "Task 2".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 13, 1, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 15, 0, 0).unwrap(),
+                break_: Duration::minutes(61),
+            },
+            TimeSheetEntry {
+                description: "Task 3, part 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 2, 8, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 2, 9, 0, 0).unwrap(),
+                break_: Duration::zero(),
+            },
+        ];
+        let result = read_csv(csv.as_bytes()).unwrap();
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn test_reader_reordered_and_additional_columns() {
+        let csv = "description,notes,date,start,end,break\nTask 1,x,01.10.22,08:00,09:00,\n";
+        let result = read_csv(csv.as_bytes()).unwrap();
+        assert_eq!(
+            result,
+            vec![TimeSheetEntry {
+                description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 8, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 9, 0, 0).unwrap(),
+                break_: Duration::zero(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_reader_billable_and_tags_columns() {
+        let csv = "date,start,end,break,description,billable,tags\n\
+                   01.10.22,08:00,09:00,,Task 1,yes,\"meeting, travel\"\n\
+                   ,09:00,10:00,,Task 2,no,\n";
+        let result = read_csv(csv.as_bytes()).unwrap();
+        let billable: Vec<_> = result.iter().map(|entry| entry.billable).collect();
+        assert_eq!(billable, vec![true, false]);
+        assert_eq!(result[0].tags, vec!["meeting", "travel"]);
+        assert!(result[1].tags.is_empty());
+    }
+
+    #[test]
+    fn test_reader_skips_absence_rows() {
+        let csv = "date,start,end,break,description\n\
+                   03.10.22,,,,Urlaub\n\
+                   04.10.22,,,,Urlaub\n\
+                   ,13:00,17:00,,Task 1\n";
+        let result = read_csv(csv.as_bytes()).unwrap();
+        assert_eq!(
+            result,
+            vec![TimeSheetEntry {
+                description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 4, 13, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 4, 17, 0, 0).unwrap(),
+                break_: Duration::zero(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_reader_entry_ending_on_next_day() {
+        let csv = "date,start,end,break,description\n01.10.22,22:00,01:30,,Task 1\n";
+        let result = read_csv(csv.as_bytes()).unwrap();
+        assert_eq!(
+            result[0].end,
+            Local.with_ymd_and_hms(2022, 10, 2, 1, 30, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        let csv = "date,start,end,description\n01.10.22,08:00,09:00,Task 1\n";
+        let result = read_csv(csv.as_bytes());
+        assert!(matches!(result, Err(ReaderError::MissingColumn("break"))));
+
+        let csv = "date,start,end,break,description\n,08:00,09:00,,Task 1\n";
+        let result = read_csv(csv.as_bytes());
+        assert!(matches!(result, Err(ReaderError::MissingDate { line: 2 })));
+
+        let csv = "date,start,end,break,description\n01.10.22,08:00,09:00,,Task 1\n\
+                   32.10.22,08:00,09:00,,Task 2\n";
+        let result = read_csv(csv.as_bytes());
+        assert!(
+            matches!(result, Err(ReaderError::InvalidDate { line: 3, value }) if value == "32.10.22")
+        );
+
+        let csv = "date,start,end,break,description\n01.10.22,8 Uhr,09:00,,Task 1\n";
+        let result = read_csv(csv.as_bytes());
+        assert!(
+            matches!(result, Err(ReaderError::InvalidTime { line: 2, value }) if value == "8 Uhr")
+        );
+
+        let csv = "date,start,end,break,description\n01.10.22,08:00,09:00,1:01,Task 1\n";
+        let result = read_csv(csv.as_bytes());
+        assert!(matches!(
+            result,
+            Err(ReaderError::BreakExceedsDuration { line: 2 })
+        ));
+    }
+
+    /// Strategy for a time sheet entry on the given date in minute precision,
+    /// which may end on the following day.
+    fn time_sheet_entry(date: NaiveDate) -> impl Strategy<Value = TimeSheetEntry> {
+        (0..24 * 60i64, 0..24 * 60i64, 0..=100u32, "(.|\n){0,20}").prop_map(
+            move |(start, duration, break_percentage, description)| {
+                let start = Local
+                    .from_local_datetime(&date.and_time(NaiveTime::MIN))
+                    .earliest()
+                    .unwrap()
+                    + Duration::minutes(start);
+                let duration = Duration::minutes(duration);
+                TimeSheetEntry {
+                    description,
+                    task: None,
+                    project: None,
+                    tags: vec![],
+                    billable: false,
+                    start,
+                    end: start + duration,
+                    break_: Duration::minutes(
+                        duration.num_minutes() * i64::from(break_percentage) / 100,
+                    ),
+                }
+            },
+        )
+    }
+
+    /// Strategy for a list of time sheet entries spread over a few days.
+    fn time_sheet_entries() -> impl Strategy<Value = Vec<TimeSheetEntry>> {
+        let first_day = NaiveDate::from_ymd_opt(2022, 10, 1).unwrap();
+        prop::collection::vec(
+            (0..5u64)
+                .prop_flat_map(move |day| time_sheet_entry(first_day + chrono::Days::new(day))),
+            0..10,
+        )
+        .prop_map(|mut entries| {
+            entries.sort_by_key(|entry| entry.start);
+            entries
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(entries in time_sheet_entries()) {
+            let mut buffer: Vec<u8> = Vec::new();
+            write_csv(&mut buffer, &entries).unwrap();
+            let result = read_csv(buffer.as_slice()).unwrap();
+            prop_assert_eq!(result, entries);
+        }
+
+        #[test]
+        fn test_round_trip_is_stable_with_seconds(
+            entries in time_sheet_entries(),
+            seconds in 0..60u32,
+        ) {
+            // Times with seconds are rounded when writing, but writing the
+            // entries read back results in the same entries. Starts rounded
+            // up to `24:00` are written as `00:00` of the following day then.
+            let entries: Vec<TimeSheetEntry> = entries
+                .into_iter()
+                .map(|mut entry| {
+                    entry.start = entry.start.with_second(seconds).unwrap();
+                    entry.break_ = Duration::zero();
+                    entry
+                })
+                .filter(|entry| entry.end >= entry.start)
+                .collect();
+            let mut buffer: Vec<u8> = Vec::new();
+            write_csv(&mut buffer, &entries).unwrap();
+            let mut second_buffer: Vec<u8> = Vec::new();
+            let result = read_csv(buffer.as_slice()).unwrap();
+            write_csv(&mut second_buffer, &result).unwrap();
+            prop_assert_eq!(read_csv(second_buffer.as_slice()).unwrap(), result);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RewriteError {
+    #[error("Invalid rewrite pattern")]
+    InvalidPattern(#[from] regex::Error),
+}
+
+/// Rewrite rule as given in the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum RewriteRuleConfig {
+    Regex {
+        pattern: String,
+        replacement: String,
+    },
+    Aliases {
+        aliases: HashMap<String, String>,
+    },
+}
+
+/// Rule rewriting the description of time sheet entries, e.g. configured as
+/// `[[transform.rewrite]]` in the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "RewriteRuleConfig")]
+pub enum RewriteRule {
+    /// Replace all matches of the regular expression, e.g. `^JIRA-\d+ review$`
+    /// with `Code review`. The replacement may refer to capture groups like
+    /// `$1`.
+    Regex { pattern: Regex, replacement: String },
+    /// Replace descriptions exactly matching a key with the value.
+    Aliases(HashMap<String, String>),
+}
+
+impl RewriteRule {
+    /// Create a rule replacing all matches of the regular expression.
+    pub fn regex(pattern: &str, replacement: &str) -> Result<Self, RewriteError> {
+        Ok(RewriteRule::Regex {
+            pattern: Regex::new(pattern)?,
+            replacement: replacement.to_owned(),
+        })
+    }
+
+    /// Apply the rule to the description.
+    pub fn apply(&self, description: &str) -> String {
+        match self {
+            RewriteRule::Regex {
+                pattern,
+                replacement,
+            } => pattern
+                .replace_all(description, replacement.as_str())
+                .into_owned(),
+            RewriteRule::Aliases(aliases) => aliases
+                .get(description)
+                .cloned()
+                .unwrap_or_else(|| description.to_owned()),
+        }
+    }
+}
+
+impl TryFrom<RewriteRuleConfig> for RewriteRule {
+    type Error = RewriteError;
+
+    fn try_from(config: RewriteRuleConfig) -> Result<Self, Self::Error> {
+        match config {
+            RewriteRuleConfig::Regex {
+                pattern,
+                replacement,
+            } => RewriteRule::regex(&pattern, &replacement),
+            RewriteRuleConfig::Aliases { aliases } => Ok(RewriteRule::Aliases(aliases)),
+        }
+    }
+}
+
+/// Apply the rules to the description in the given order, so each rule sees
+/// the result of the previous ones. Surrounding whitespace left by the
+/// replacements is removed.
+pub fn rewrite_description(rules: &[RewriteRule], description: &str) -> String {
+    rules
+        .iter()
+        .fold(description.trim().to_owned(), |description, rule| {
+            rule.apply(&description).trim().to_owned()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_description() {
+        let rules = vec![
+            RewriteRule::regex(r"(?i)^fix(ed)? bug #\d+.*$", "Bug fixing").unwrap(),
+            RewriteRule::regex(r"^[A-Z]+-\d+ (\w+)$", "$1").unwrap(),
+            RewriteRule::Aliases(HashMap::from([(
+                "review".to_string(),
+                "Code review".to_string(),
+            )])),
+        ];
+        assert_eq!(
+            rewrite_description(&rules, "fix bug #123 again"),
+            "Bug fixing"
+        );
+        assert_eq!(
+            rewrite_description(&rules, "JIRA-456 review"),
+            "Code review"
+        );
+        assert_eq!(rewrite_description(&rules, " review "), "Code review");
+        assert_eq!(rewrite_description(&rules, "reviews"), "reviews");
+    }
+
+    #[test]
+    fn test_rewrite_rules_from_toml() {
+        #[derive(Deserialize)]
+        struct Config {
+            rewrite: Vec<RewriteRule>,
+        }
+        let config: Config = toml::from_str(
+            r#"
+            [[rewrite]]
+            pattern = '^JIRA-\d+ '
+            replacement = ""
+
+            [[rewrite]]
+            aliases = { review = "Code review" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            rewrite_description(&config.rewrite, "JIRA-456 review"),
+            "Code review"
+        );
+        assert!(toml::from_str::<Config>(
+            r#"
+            [[rewrite]]
+            pattern = "("
+            replacement = ""
+            "#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,518 @@
+use crate::{
+    absence::{Absence, AbsenceCredit},
+    holidays::Holidays,
+    output::{Output, OutputError, WriteOptions},
+};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Weekday};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ScheduleError {
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+    #[error("Invalid state file")]
+    Deserialization(#[from] toml::de::Error),
+    #[error("State file serialization error")]
+    Serialization(#[from] toml::ser::Error),
+    #[error("State file output error")]
+    Output(#[from] OutputError),
+}
+
+/// Working days used for `weekly_hours`, if not configured otherwise.
+const DEFAULT_WORKING_DAYS: [Weekday; 5] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+];
+
+/// Work schedule defining the target working time of each day, configured in
+/// the `[schedule]` table of the config file.
+/// - `daily_hours` sets the target hours per weekday, e.g. for part-time
+///   patterns like `{ mon = 8, tue = 8, wed = 4 }`. Other weekdays have no
+///   target.
+/// - Otherwise, `weekly_hours` are distributed equally over the
+///   `working_days` (default: Monday to Friday).
+///
+/// Public holidays and extra days off configured as `holidays` have no target.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WorkSchedule {
+    pub weekly_hours: Option<f64>,
+    pub working_days: Option<Vec<Weekday>>,
+    pub daily_hours: HashMap<Weekday, f64>,
+    /// Flextime balance in hours carried over from the previous month, used
+    /// if the state file contains no balance of the previous month.
+    pub balance: f64,
+    /// File keeping the flextime balance at the end of each month, so it is
+    /// carried over into the next month automatically.
+    pub state_file: Option<PathBuf>,
+    pub holidays: Holidays,
+}
+
+impl WorkSchedule {
+    /// Target working time of the given day, zero on holidays.
+    pub fn target(&self, date: NaiveDate) -> Duration {
+        if self.holidays.holiday(date).is_some() {
+            return Duration::zero();
+        }
+        let weekday = date.weekday();
+        if !self.daily_hours.is_empty() {
+            return self
+                .daily_hours
+                .get(&weekday)
+                .map_or_else(Duration::zero, |hours| duration_from_hours(*hours));
+        }
+        let working_days = self
+            .working_days
+            .as_deref()
+            .unwrap_or(&DEFAULT_WORKING_DAYS);
+        match self.weekly_hours {
+            Some(weekly_hours) if working_days.contains(&weekday) => {
+                duration_from_hours(weekly_hours / working_days.len() as f64)
+            }
+            _ => Duration::zero(),
+        }
+    }
+
+    /// Whether the given day is a working day, which is no holiday. Without
+    /// targets, Monday to Friday are working days.
+    pub fn is_working_day(&self, date: NaiveDate) -> bool {
+        if self.holidays.holiday(date).is_some() {
+            return false;
+        }
+        let weekday = date.weekday();
+        if !self.daily_hours.is_empty() {
+            return self
+                .daily_hours
+                .get(&weekday)
+                .is_some_and(|hours| *hours > 0.0);
+        }
+        self.working_days
+            .as_deref()
+            .unwrap_or(&DEFAULT_WORKING_DAYS)
+            .contains(&weekday)
+    }
+}
+
+/// Target and actual working time of a day.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DayBalance {
+    pub date: NaiveDate,
+    /// Name of the holiday, if the day is one.
+    pub holiday: Option<String>,
+    /// Label of the absence, if absent on the day.
+    pub absence: Option<String>,
+    pub target: Duration,
+    pub actual: Duration,
+}
+
+impl DayBalance {
+    /// Working time exceeding the target, negative if below the target.
+    pub fn overtime(&self) -> Duration {
+        self.actual - self.target
+    }
+}
+
+/// Target and actual working time of a week.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WeekBalance {
+    /// ISO week, e.g. `2022-W40`.
+    pub week: String,
+    pub target: Duration,
+    pub actual: Duration,
+}
+
+impl WeekBalance {
+    /// Working time exceeding the target, negative if below the target.
+    pub fn overtime(&self) -> Duration {
+        self.actual - self.target
+    }
+}
+
+/// Target and actual working time of each day of a period together with the
+/// flextime balance carried over into the period.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScheduleReport {
+    pub opening_balance: Duration,
+    pub days: Vec<DayBalance>,
+}
+
+impl ScheduleReport {
+    /// Compare the working time per day with the targets of the schedule for
+    /// all days from `start` until `end` (exclusive).
+    pub fn new(
+        schedule: &WorkSchedule,
+        start: NaiveDate,
+        end: NaiveDate,
+        working_time: &BTreeMap<NaiveDate, Duration>,
+        opening_balance: Duration,
+    ) -> Self {
+        let days = start
+            .iter_days()
+            .take_while(|date| *date < end)
+            .map(|date| DayBalance {
+                date,
+                holiday: schedule.holidays.holiday(date),
+                absence: None,
+                target: schedule.target(date),
+                actual: working_time
+                    .get(&date)
+                    .copied()
+                    .unwrap_or_else(Duration::zero),
+            })
+            .collect();
+        ScheduleReport {
+            opening_balance,
+            days,
+        }
+    }
+
+    /// Mark the days of the absences. Absences fulfilling the target reduce
+    /// the target of the day to zero, or to half of it for half days.
+    pub fn with_absences(mut self, absences: &[Absence]) -> Self {
+        for absence in absences {
+            let Some(day) = self.days.iter_mut().find(|day| day.date == absence.date) else {
+                continue;
+            };
+            day.absence = Some(absence.label.clone());
+            if absence.credit == AbsenceCredit::Target {
+                day.target = if absence.half_day {
+                    day.target / 2
+                } else {
+                    Duration::zero()
+                };
+            }
+        }
+        self
+    }
+
+    /// Target and actual working time of the given day.
+    pub fn day(&self, date: NaiveDate) -> Option<&DayBalance> {
+        self.days.iter().find(|day| day.date == date)
+    }
+
+    /// Target and actual working time per ISO week, ordered by week. Weeks
+    /// only partially covered by the period are limited to the covered days.
+    pub fn weeks(&self) -> Vec<WeekBalance> {
+        let mut weeks: Vec<WeekBalance> = vec![];
+        for day in &self.days {
+            let week = day.date.iso_week();
+            let week = format!("{}-W{:02}", week.year(), week.week());
+            match weeks.last_mut() {
+                Some(last) if last.week == week => {
+                    last.target = last.target + day.target;
+                    last.actual = last.actual + day.actual;
+                }
+                _ => weeks.push(WeekBalance {
+                    week,
+                    target: day.target,
+                    actual: day.actual,
+                }),
+            }
+        }
+        weeks
+    }
+
+    pub fn target(&self) -> Duration {
+        sum(self.days.iter().map(|day| day.target))
+    }
+
+    pub fn actual(&self) -> Duration {
+        sum(self.days.iter().map(|day| day.actual))
+    }
+
+    /// Working time of the period exceeding the target, negative if below.
+    pub fn overtime(&self) -> Duration {
+        self.actual() - self.target()
+    }
+
+    /// Flextime balance at the end of the given day.
+    pub fn balance_at(&self, date: NaiveDate) -> Duration {
+        self.opening_balance
+            + sum(self
+                .days
+                .iter()
+                .take_while(|day| day.date <= date)
+                .map(DayBalance::overtime))
+    }
+
+    /// Flextime balance at the end of the period.
+    pub fn closing_balance(&self) -> Duration {
+        self.opening_balance + self.overtime()
+    }
+}
+
+/// Sum up the working time per day of the start of the given items of start
+/// time and working time.
+pub fn daily_working_time(
+    items: impl IntoIterator<Item = (DateTime<Local>, Duration)>,
+) -> BTreeMap<NaiveDate, Duration> {
+    let mut result: BTreeMap<NaiveDate, Duration> = BTreeMap::new();
+    for (start, duration) in items {
+        let total = result
+            .entry(start.date_naive())
+            .or_insert_with(Duration::zero);
+        *total = *total + duration;
+    }
+    result
+}
+
+/// Read the flextime balance at the end of the month preceding the given
+/// month from the state file. Returns `None` if the file or the balance does
+/// not exist.
+pub fn read_balance(path: &Path, year: i32, month: u32) -> Result<Option<Duration>, ScheduleError> {
+    let (year, month) = if month == 1 {
+        (year - 1, 12)
+    } else {
+        (year, month - 1)
+    };
+    Ok(read_state(path)?
+        .get(&state_key(year, month))
+        .map(|hours| duration_from_hours(*hours)))
+}
+
+/// Store the flextime balance at the end of the given month in the state file,
+/// keeping the balances of other months. The file is replaced atomically (see
+/// `Output::write`), so an interrupted run never loses the balances.
+pub fn write_balance(
+    path: &Path,
+    year: i32,
+    month: u32,
+    balance: Duration,
+) -> Result<(), ScheduleError> {
+    let mut state = read_state(path)?;
+    state.insert(state_key(year, month), balance.num_minutes() as f64 / 60.0);
+    let state = toml::to_string(&state)?;
+    let options = WriteOptions {
+        force: true,
+        backup: false,
+    };
+    Output::File(path.to_owned()).write(&options, |wtr| Ok(wtr.write_all(state.as_bytes())?))?;
+    Ok(())
+}
+
+/// Read the balances of the state file by month, empty if it does not exist.
+fn read_state(path: &Path) -> Result<BTreeMap<String, f64>, ScheduleError> {
+    match fs::read_to_string(path) {
+        Ok(state) => Ok(toml::from_str(&state)?),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+fn state_key(year: i32, month: u32) -> String {
+    format!("{year}-{month:02}")
+}
+
+/// Convert hours into a duration, rounded to the second.
+pub fn duration_from_hours(hours: f64) -> Duration {
+    Duration::seconds((hours * 3600.0).round() as i64)
+}
+
+fn sum(durations: impl Iterator<Item = Duration>) -> Duration {
+    durations.fold(Duration::zero(), |total, duration| total + duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2022, 10, day).unwrap()
+    }
+
+    #[test]
+    fn test_schedule_targets() {
+        let schedule = WorkSchedule {
+            weekly_hours: Some(40.0),
+            ..Default::default()
+        };
+        // 2022-10-03 is a Monday.
+        assert_eq!(schedule.target(date(3)), Duration::hours(8));
+        assert_eq!(schedule.target(date(8)), Duration::zero());
+
+        let part_time = WorkSchedule {
+            weekly_hours: Some(20.0),
+            working_days: Some(vec![Weekday::Mon, Weekday::Tue, Weekday::Wed]),
+            ..Default::default()
+        };
+        assert_eq!(
+            part_time.target(date(4)),
+            Duration::hours(6) + Duration::minutes(40)
+        );
+        assert_eq!(part_time.target(date(6)), Duration::zero());
+
+        let daily: WorkSchedule = toml::from_str(
+            r#"
+            weekly_hours = 40
+            daily_hours = { mon = 8, fri = 4.5 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(daily.target(date(3)), Duration::hours(8));
+        assert_eq!(daily.target(date(4)), Duration::zero());
+        assert_eq!(
+            daily.target(date(7)),
+            Duration::hours(4) + Duration::minutes(30)
+        );
+    }
+
+    #[test]
+    fn test_schedule_without_target_on_holidays() {
+        let schedule: WorkSchedule = toml::from_str(
+            r#"
+            weekly_hours = 40
+            [holidays]
+            region = "DE-BY"
+            extra = { 2022-10-04 = "Betriebsausflug" }
+            "#,
+        )
+        .unwrap();
+        let report = ScheduleReport::new(
+            &schedule,
+            date(3),
+            date(6),
+            &BTreeMap::new(),
+            Duration::zero(),
+        );
+        assert_eq!(
+            report
+                .days
+                .iter()
+                .map(|day| (day.holiday.as_deref(), day.target.num_hours()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some("Tag der Deutschen Einheit"), 0),
+                (Some("Betriebsausflug"), 0),
+                (None, 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_schedule_report() {
+        let schedule = WorkSchedule {
+            weekly_hours: Some(40.0),
+            ..Default::default()
+        };
+        let working_time = daily_working_time([
+            (
+                Local.with_ymd_and_hms(2022, 10, 2, 10, 0, 0).unwrap(),
+                Duration::hours(2),
+            ),
+            (
+                Local.with_ymd_and_hms(2022, 10, 3, 8, 0, 0).unwrap(),
+                Duration::hours(6),
+            ),
+            (
+                Local.with_ymd_and_hms(2022, 10, 3, 15, 0, 0).unwrap(),
+                Duration::hours(3),
+            ),
+        ]);
+        // Saturday, 2022-10-01, until Tuesday, 2022-10-04.
+        let report = ScheduleReport::new(
+            &schedule,
+            date(1),
+            date(5),
+            &working_time,
+            Duration::minutes(30),
+        );
+        assert_eq!(report.target(), Duration::hours(16));
+        assert_eq!(report.actual(), Duration::hours(11));
+        assert_eq!(report.overtime(), Duration::hours(-5));
+        assert_eq!(report.day(date(3)).unwrap().overtime(), Duration::hours(1));
+        assert_eq!(
+            report.balance_at(date(3)),
+            Duration::hours(3) + Duration::minutes(30)
+        );
+        assert_eq!(
+            report.closing_balance(),
+            Duration::minutes(30) - Duration::hours(5)
+        );
+        assert_eq!(
+            report.weeks(),
+            vec![
+                WeekBalance {
+                    week: "2022-W39".to_string(),
+                    target: Duration::zero(),
+                    actual: Duration::hours(2),
+                },
+                WeekBalance {
+                    week: "2022-W40".to_string(),
+                    target: Duration::hours(16),
+                    actual: Duration::hours(9),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_schedule_report_with_absences() {
+        let schedule = WorkSchedule {
+            weekly_hours: Some(40.0),
+            ..Default::default()
+        };
+        let absence = |day, credit, half_day| Absence {
+            date: date(day),
+            label: "Urlaub".to_string(),
+            credit,
+            half_day,
+        };
+        let report = ScheduleReport::new(
+            &schedule,
+            date(3),
+            date(6),
+            &BTreeMap::new(),
+            Duration::zero(),
+        )
+        .with_absences(&[
+            absence(3, AbsenceCredit::Target, false),
+            absence(4, AbsenceCredit::Target, true),
+            absence(5, AbsenceCredit::None, false),
+        ]);
+        assert_eq!(
+            report
+                .days
+                .iter()
+                .map(|day| (day.absence.as_deref(), day.target.num_hours()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some("Urlaub"), 0),
+                (Some("Urlaub"), 4),
+                (Some("Urlaub"), 8)
+            ]
+        );
+        assert_eq!(report.closing_balance(), Duration::hours(-12));
+    }
+
+    #[test]
+    fn test_read_and_write_balance() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flextime.toml");
+        assert_eq!(read_balance(&path, 2023, 1).unwrap(), None);
+
+        write_balance(&path, 2022, 12, Duration::minutes(-90)).unwrap();
+        write_balance(&path, 2023, 1, Duration::minutes(45)).unwrap();
+        assert_eq!(
+            read_balance(&path, 2023, 1).unwrap(),
+            Some(Duration::minutes(-90))
+        );
+        assert_eq!(
+            read_balance(&path, 2023, 2).unwrap(),
+            Some(Duration::minutes(45))
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2022-12 = -1.5\n2023-01 = 0.75\n"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    clockify::ClockifyError, harvest::HarvestError, import::ImportError, kimai::KimaiError,
+    text_log::TextLogError, timewarrior::TimewarriorError, toggl::TogglError,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, Months, NaiveDate};
+use std::error;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SourceError {
+    #[error("Clockify error")]
+    Clockify(#[from] ClockifyError),
+    #[error("Import error")]
+    Import(#[from] ImportError),
+    #[error("Toggl error")]
+    Toggl(#[from] TogglError),
+    #[error("Kimai error")]
+    Kimai(#[from] KimaiError),
+    #[error("Harvest error")]
+    Harvest(#[from] HarvestError),
+    #[error("Timewarrior error")]
+    Timewarrior(#[from] TimewarriorError),
+    #[error("Text log error")]
+    TextLog(#[from] TextLogError),
+    #[error(transparent)]
+    Other(#[from] Box<dyn error::Error + Send + Sync>),
+}
+
+/// Time entry as provided by a time source, independent of the time tracker.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    pub description: String,
+    pub project: Option<String>,
+    /// Client (customer) the project belongs to.
+    pub client: Option<String>,
+    pub task: Option<String>,
+    pub tags: Vec<String>,
+    pub billable: bool,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+}
+
+/// Selection of the time entries to retrieve from a time source.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Query {
+    /// First day of the time entries to retrieve.
+    pub start: NaiveDate,
+    /// Day after the last day of the time entries to retrieve.
+    pub end: NaiveDate,
+    /// Only retrieve billable (`true`) or non-billable (`false`) time entries,
+    /// if given.
+    pub billable: Option<bool>,
+    /// Only retrieve time entries with at least one of these tags, if not
+    /// empty.
+    pub tags: Vec<String>,
+}
+
+impl Query {
+    /// Query for the time entries of the given month. Returns `None` for an
+    /// invalid month.
+    pub fn month(year: u32, month: u32) -> Option<Self> {
+        let start = NaiveDate::from_ymd_opt(i32::try_from(year).ok()?, month, 1)?;
+        let end = start.checked_add_months(Months::new(1))?;
+        Some(Self {
+            start,
+            end,
+            billable: None,
+            tags: vec![],
+        })
+    }
+
+    /// Whether the given time lies within the queried days.
+    pub fn contains(&self, time: &DateTime<Local>) -> bool {
+        let date = time.date_naive();
+        self.start <= date && date < self.end
+    }
+
+    /// Whether the given entry is selected by the query. Used by time sources
+    /// to filter entries which cannot be filtered by the time tracker itself.
+    pub fn matches(&self, entry: &Entry) -> bool {
+        self.contains(&entry.start)
+            && self
+                .billable
+                .is_none_or(|billable| entry.billable == billable)
+            && (self.tags.is_empty() || entry.tags.iter().any(|tag| self.tags.contains(tag)))
+    }
+}
+
+/// Source of time entries, e.g. a time tracking service or an export file.
+#[async_trait]
+pub trait TimeSource: Send + Sync {
+    /// Retrieve the time entries starting within the queried days, ordered by
+    /// start time.
+    async fn retrieve(&self, query: &Query) -> Result<Vec<Entry>, SourceError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_query_month() {
+        let query = Query::month(2022, 10).unwrap();
+        assert_eq!(query.start, NaiveDate::from_ymd_opt(2022, 10, 1).unwrap());
+        assert_eq!(query.end, NaiveDate::from_ymd_opt(2022, 11, 1).unwrap());
+        let query = Query::month(2022, 12).unwrap();
+        assert_eq!(query.end, NaiveDate::from_ymd_opt(2023, 1, 1).unwrap());
+        assert_eq!(Query::month(2022, 13), None);
+        assert_eq!(Query::month(2022, 0), None);
+    }
+
+    #[test]
+    fn test_query_contains() {
+        let query = Query::month(2022, 10).unwrap();
+        let time = Local.with_ymd_and_hms(2022, 9, 30, 23, 59, 59).unwrap();
+        assert!(!query.contains(&time));
+        let time = Local.with_ymd_and_hms(2022, 10, 1, 0, 0, 0).unwrap();
+        assert!(query.contains(&time));
+        let time = Local.with_ymd_and_hms(2022, 10, 31, 23, 59, 59).unwrap();
+        assert!(query.contains(&time));
+        let time = Local.with_ymd_and_hms(2022, 11, 1, 0, 0, 0).unwrap();
+        assert!(!query.contains(&time));
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let entry = Entry {
+            description: "Entry 1".to_string(),
+            project: None,
+            client: None,
+            task: None,
+            tags: vec!["meeting".to_string()],
+            billable: true,
+            start: Local.with_ymd_and_hms(2022, 10, 1, 8, 0, 0).unwrap(),
+            end: Local.with_ymd_and_hms(2022, 10, 1, 9, 0, 0).unwrap(),
+        };
+        let query = Query::month(2022, 10).unwrap();
+        assert!(query.matches(&entry));
+        let query = Query {
+            billable: Some(true),
+            ..query
+        };
+        assert!(query.matches(&entry));
+        let query = Query {
+            billable: Some(false),
+            ..query
+        };
+        assert!(!query.matches(&entry));
+        let query = Query {
+            tags: vec!["travel".to_string(), "meeting".to_string()],
+            ..Query::month(2022, 10).unwrap()
+        };
+        assert!(query.matches(&entry));
+        let query = Query {
+            tags: vec!["travel".to_string()],
+            ..query
+        };
+        assert!(!query.matches(&entry));
+        let query = Query::month(2022, 9).unwrap();
+        assert!(!query.matches(&entry));
+    }
+}
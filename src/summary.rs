@@ -0,0 +1,478 @@
+use crate::{schedule::ScheduleReport, source::Entry, transform::TimeSheetEntry};
+use chrono::{DateTime, Datelike, Duration, Local};
+use serde::Serialize;
+use std::{collections::BTreeMap, io};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SummaryError {
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+    #[error("CSV error")]
+    Csv(#[from] csv::Error),
+    #[error("JSON serialization error")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Criteria the working time is summed up by.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Grouping {
+    Task,
+    Project,
+    Tag,
+    /// ISO week of the start of the entries, e.g. `2022-W40`.
+    Week,
+    Billable,
+}
+
+impl Grouping {
+    /// All groupings in the order they are reported.
+    pub const ALL: [Grouping; 5] = [
+        Grouping::Task,
+        Grouping::Project,
+        Grouping::Tag,
+        Grouping::Week,
+        Grouping::Billable,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Grouping::Task => "task",
+            Grouping::Project => "project",
+            Grouping::Tag => "tag",
+            Grouping::Week => "week",
+            Grouping::Billable => "billable",
+        }
+    }
+}
+
+/// Working time of a single entry with the attributes used for grouping.
+struct Record<'a> {
+    task: Option<&'a str>,
+    project: Option<&'a str>,
+    tags: &'a [String],
+    billable: bool,
+    start: DateTime<Local>,
+    duration: Duration,
+}
+
+impl Record<'_> {
+    /// Names of the groups of the given grouping the record belongs to. An
+    /// entry with several tags counts towards each of them.
+    fn keys(&self, grouping: Grouping) -> Vec<String> {
+        match grouping {
+            Grouping::Task => vec![self.task.unwrap_or("(no task)").to_owned()],
+            Grouping::Project => vec![self.project.unwrap_or("(no project)").to_owned()],
+            Grouping::Tag if self.tags.is_empty() => vec!["(no tag)".to_owned()],
+            Grouping::Tag => self.tags.to_vec(),
+            Grouping::Week => {
+                let week = self.start.iso_week();
+                vec![format!("{}-W{:02}", week.year(), week.week())]
+            }
+            Grouping::Billable => vec![if self.billable {
+                "billable"
+            } else {
+                "non-billable"
+            }
+            .to_owned()],
+        }
+    }
+}
+
+/// Working time summed up per task, project, tag, week and billable flag.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Summary {
+    pub total: Duration,
+    /// Working time per group name for each grouping, ordered by name.
+    pub groups: BTreeMap<Grouping, BTreeMap<String, Duration>>,
+    /// Target and actual working time according to the work schedule, if
+    /// configured.
+    pub schedule: Option<ScheduleReport>,
+}
+
+impl Summary {
+    /// Summarize time entries of a time source.
+    pub fn from_entries(entries: &[Entry]) -> Self {
+        Self::from_records(entries.iter().map(|entry| Record {
+            task: entry.task.as_deref(),
+            project: entry.project.as_deref(),
+            tags: &entry.tags,
+            billable: entry.billable,
+            start: entry.start,
+            duration: entry.end - entry.start,
+        }))
+    }
+
+    /// Summarize time sheet entries, e.g. read from an existing time sheet.
+    pub fn from_time_sheet_entries(entries: &[TimeSheetEntry]) -> Self {
+        Self::from_records(entries.iter().map(|entry| Record {
+            task: entry.task.as_deref(),
+            project: entry.project.as_deref(),
+            tags: &entry.tags,
+            billable: entry.billable,
+            start: entry.start,
+            duration: entry.end - entry.start - entry.break_,
+        }))
+    }
+
+    fn from_records<'a>(records: impl Iterator<Item = Record<'a>>) -> Self {
+        let mut total = Duration::zero();
+        let mut groups: BTreeMap<Grouping, BTreeMap<String, Duration>> = Grouping::ALL
+            .into_iter()
+            .map(|grouping| (grouping, BTreeMap::new()))
+            .collect();
+        for record in records {
+            total = total + record.duration;
+            for (grouping, durations) in &mut groups {
+                for key in record.keys(*grouping) {
+                    let duration = durations.entry(key).or_insert_with(Duration::zero);
+                    *duration = *duration + record.duration;
+                }
+            }
+        }
+        Summary {
+            total,
+            groups,
+            schedule: None,
+        }
+    }
+
+    /// Write the summary as plain-text table with one section per grouping.
+    pub fn write_table<W: io::Write>(&self, mut wtr: W) -> Result<(), SummaryError> {
+        let width = self
+            .groups
+            .values()
+            .flat_map(BTreeMap::keys)
+            .map(|name| name.chars().count())
+            .chain(["Total".len()])
+            .chain(self.schedule.as_ref().map(|_| "Previous balance".len()))
+            .max()
+            .unwrap_or_default();
+        for (grouping, durations) in &self.groups {
+            writeln!(wtr, "{}", capitalize(grouping.name()))?;
+            for (name, duration) in durations {
+                writeln!(wtr, "  {name:<width$}  {:>7}", format_duration(duration))?;
+            }
+            writeln!(wtr)?;
+        }
+        if let Some(schedule) = &self.schedule {
+            writeln!(
+                wtr,
+                "{:<heading$}  {:>7}  {:>7}  {:>7}",
+                "Schedule",
+                "target",
+                "actual",
+                "overtime",
+                heading = width + 2
+            )?;
+            for week in schedule.weeks() {
+                writeln!(
+                    wtr,
+                    "  {:<width$}  {:>7}  {:>7}  {:>7}",
+                    week.week,
+                    format_duration(&week.target),
+                    format_duration(&week.actual),
+                    format_duration(&week.overtime())
+                )?;
+            }
+            writeln!(
+                wtr,
+                "  {:<width$}  {:>7}  {:>7}  {:>7}",
+                "Month",
+                format_duration(&schedule.target()),
+                format_duration(&schedule.actual()),
+                format_duration(&schedule.overtime())
+            )?;
+            writeln!(
+                wtr,
+                "  {:<width$}  {:>7}",
+                "Previous balance",
+                format_duration(&schedule.opening_balance)
+            )?;
+            writeln!(
+                wtr,
+                "  {:<width$}  {:>7}",
+                "Balance",
+                format_duration(&schedule.closing_balance())
+            )?;
+            writeln!(wtr)?;
+        }
+        writeln!(
+            wtr,
+            "  {:<width$}  {:>7}",
+            "Total",
+            format_duration(&self.total)
+        )?;
+        Ok(())
+    }
+
+    /// Write the summary as CSV with the columns `group`, `name` and `hours`
+    /// (decimal), ending with the total.
+    pub fn write_csv<W: io::Write>(&self, wtr: W) -> Result<(), SummaryError> {
+        let mut wtr = csv::Writer::from_writer(wtr);
+        wtr.write_record(["group", "name", "hours"])?;
+        for (grouping, durations) in &self.groups {
+            for (name, duration) in durations {
+                wtr.write_record([grouping.name(), name, &format_hours(duration)])?;
+            }
+        }
+        if let Some(schedule) = &self.schedule {
+            let weeks = schedule.weeks();
+            let periods = weeks
+                .iter()
+                .map(|week| (week.week.as_str(), week.target, week.actual))
+                .chain([("month", schedule.target(), schedule.actual())]);
+            for (period, target, actual) in periods {
+                wtr.write_record(["target", period, &format_hours(&target)])?;
+                wtr.write_record(["actual", period, &format_hours(&actual)])?;
+                wtr.write_record(["overtime", period, &format_hours(&(actual - target))])?;
+            }
+            wtr.write_record([
+                "balance",
+                "previous",
+                &format_hours(&schedule.opening_balance),
+            ])?;
+            wtr.write_record([
+                "balance",
+                "month",
+                &format_hours(&schedule.closing_balance()),
+            ])?;
+        }
+        wtr.write_record(["total", "", &format_hours(&self.total)])?;
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// Write the summary as JSON object with the total hours and the hours per
+    /// group name for each grouping.
+    pub fn write_json<W: io::Write>(&self, mut wtr: W) -> Result<(), SummaryError> {
+        #[derive(Serialize)]
+        struct JsonBalance {
+            target: f64,
+            actual: f64,
+            overtime: f64,
+        }
+        #[derive(Serialize)]
+        struct JsonSchedule {
+            weeks: BTreeMap<String, JsonBalance>,
+            month: JsonBalance,
+            previous_balance: f64,
+            balance: f64,
+        }
+        #[derive(Serialize)]
+        struct JsonSummary<'a> {
+            total_hours: f64,
+            #[serde(flatten)]
+            groups: BTreeMap<&'a str, BTreeMap<&'a str, f64>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            schedule: Option<JsonSchedule>,
+        }
+        let balance = |target: Duration, actual: Duration| JsonBalance {
+            target: hours(&target),
+            actual: hours(&actual),
+            overtime: hours(&(actual - target)),
+        };
+        let summary = JsonSummary {
+            total_hours: hours(&self.total),
+            schedule: self.schedule.as_ref().map(|schedule| JsonSchedule {
+                weeks: schedule
+                    .weeks()
+                    .into_iter()
+                    .map(|week| (week.week, balance(week.target, week.actual)))
+                    .collect(),
+                month: balance(schedule.target(), schedule.actual()),
+                previous_balance: hours(&schedule.opening_balance),
+                balance: hours(&schedule.closing_balance()),
+            }),
+            groups: self
+                .groups
+                .iter()
+                .map(|(grouping, durations)| {
+                    (
+                        grouping.name(),
+                        durations
+                            .iter()
+                            .map(|(name, duration)| (name.as_str(), hours(duration)))
+                            .collect(),
+                    )
+                })
+                .collect(),
+        };
+        serde_json::to_writer_pretty(&mut wtr, &summary)?;
+        writeln!(wtr)?;
+        Ok(())
+    }
+}
+
+/// Duration in decimal hours, rounded to two decimal places.
+fn hours(duration: &Duration) -> f64 {
+    (duration.num_minutes() as f64 / 60.0 * 100.0).round() / 100.0
+}
+
+fn format_hours(duration: &Duration) -> String {
+    format!("{:.2}", hours(duration))
+}
+
+fn format_duration(duration: &Duration) -> String {
+    let minutes = duration.num_minutes();
+    let sign = if minutes < 0 { "-" } else { "" };
+    let minutes = minutes.abs();
+    format!("{sign}{}:{:02}", minutes / 60, minutes % 60)
+}
+
+fn capitalize(value: &str) -> String {
+    let mut chars = value.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry {
+                description: "Entry 1".to_string(),
+                project: Some("Project 1".to_string()),
+                client: None,
+                task: Some("Support".to_string()),
+                tags: vec!["meeting".to_string(), "travel".to_string()],
+                billable: true,
+                start: Local.with_ymd_and_hms(2022, 10, 2, 8, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 2, 9, 30, 0).unwrap(),
+            },
+            Entry {
+                description: "Entry 2".to_string(),
+                project: None,
+                client: None,
+                task: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 3, 8, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 3, 8, 45, 0).unwrap(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_summary_from_entries() {
+        let summary = Summary::from_entries(&entries());
+        assert_eq!(summary.total, Duration::minutes(135));
+        let group = |grouping| {
+            summary.groups[&grouping]
+                .iter()
+                .map(|(name, duration)| (name.as_str(), duration.num_minutes()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            group(Grouping::Task),
+            vec![("(no task)", 45), ("Support", 90)]
+        );
+        assert_eq!(
+            group(Grouping::Project),
+            vec![("(no project)", 45), ("Project 1", 90)]
+        );
+        assert_eq!(
+            group(Grouping::Tag),
+            vec![("(no tag)", 45), ("meeting", 90), ("travel", 90)]
+        );
+        assert_eq!(
+            group(Grouping::Week),
+            vec![("2022-W39", 90), ("2022-W40", 45)]
+        );
+        assert_eq!(
+            group(Grouping::Billable),
+            vec![("billable", 90), ("non-billable", 45)]
+        );
+    }
+
+    #[test]
+    fn test_summary_from_time_sheet_entries_excludes_breaks() {
+        let entries = vec![TimeSheetEntry {
+            description: "Task 1".to_string(),
+            task: Some("Task 1".to_string()),
+            project: Some("Project A".to_string()),
+            tags: vec![],
+            billable: false,
+            start: Local.with_ymd_and_hms(2022, 10, 1, 8, 0, 0).unwrap(),
+            end: Local.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap(),
+            break_: Duration::minutes(30),
+        }];
+        let summary = Summary::from_time_sheet_entries(&entries);
+        assert_eq!(summary.total, Duration::minutes(210));
+        assert_eq!(
+            summary.groups[&Grouping::Task]["Task 1"],
+            Duration::minutes(210)
+        );
+        assert_eq!(
+            summary.groups[&Grouping::Project]["Project A"],
+            Duration::minutes(210)
+        );
+    }
+
+    #[test]
+    fn test_write_summary() {
+        let summary = Summary::from_entries(&entries());
+
+        let mut buffer: Vec<u8> = Vec::new();
+        summary.write_csv(&mut buffer).unwrap();
+        let csv = String::from_utf8(buffer).unwrap();
+        assert!(csv.starts_with("group,name,hours\ntask,(no task),0.75\ntask,Support,1.50\n"));
+        assert!(csv.ends_with("billable,non-billable,0.75\ntotal,,2.25\n"));
+
+        let mut buffer: Vec<u8> = Vec::new();
+        summary.write_json(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["total_hours"], 2.25);
+        assert_eq!(json["tag"]["meeting"], 1.5);
+
+        let mut buffer: Vec<u8> = Vec::new();
+        summary.write_table(&mut buffer).unwrap();
+        let table = String::from_utf8(buffer).unwrap();
+        assert!(table.starts_with("Task\n  (no task)        0:45\n  Support          1:30\n"));
+        assert!(table.ends_with("\n  Total            2:15\n"));
+    }
+
+    #[test]
+    fn test_write_summary_with_schedule() {
+        let mut summary = Summary::from_entries(&entries());
+        let schedule = crate::schedule::WorkSchedule {
+            weekly_hours: Some(40.0),
+            ..Default::default()
+        };
+        summary.schedule = Some(ScheduleReport::new(
+            &schedule,
+            NaiveDate::from_ymd_opt(2022, 10, 2).unwrap(),
+            NaiveDate::from_ymd_opt(2022, 10, 4).unwrap(),
+            &crate::schedule::daily_working_time(
+                entries()
+                    .iter()
+                    .map(|entry| (entry.start, entry.end - entry.start)),
+            ),
+            Duration::hours(1),
+        ));
+
+        let mut buffer: Vec<u8> = Vec::new();
+        summary.write_csv(&mut buffer).unwrap();
+        let csv = String::from_utf8(buffer).unwrap();
+        assert!(csv.contains("\novertime,2022-W40,-7.25\n"));
+        assert!(csv.contains("\nbalance,month,-4.75\n"));
+
+        let mut buffer: Vec<u8> = Vec::new();
+        summary.write_json(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["schedule"]["month"]["target"], 8.0);
+        assert_eq!(json["schedule"]["weeks"]["2022-W39"]["overtime"], 1.5);
+        assert_eq!(json["schedule"]["balance"], -4.75);
+
+        let mut buffer: Vec<u8> = Vec::new();
+        summary.write_table(&mut buffer).unwrap();
+        let table = String::from_utf8(buffer).unwrap();
+        assert!(table.contains("\n  Month                8:00     2:15    -5:45\n"));
+        assert!(table.contains("\n  Balance             -4:45\n"));
+    }
+}
@@ -0,0 +1,299 @@
+use crate::source::Entry;
+use serde::Deserialize;
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum TemplateError {
+    #[error("Unknown placeholder {{{0}}} in description template")]
+    UnknownPlaceholder(String),
+    #[error("Unclosed placeholder in description template")]
+    Unclosed,
+}
+
+/// Placeholder of a description template, replaced by the corresponding field
+/// of a time entry.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Placeholder {
+    Description,
+    Task,
+    Tags,
+    Project,
+    Client,
+}
+
+impl Placeholder {
+    /// Value of the placeholder for the given time entry, `None` if missing or
+    /// empty.
+    fn value(self, entry: &Entry) -> Option<String> {
+        let value = match self {
+            Placeholder::Description => Some(entry.description.clone()),
+            Placeholder::Task => entry.task.clone(),
+            Placeholder::Tags => Some(entry.tags.join(", ")),
+            Placeholder::Project => entry.project.clone(),
+            Placeholder::Client => entry.client.clone(),
+        };
+        value.filter(|value| !value.trim().is_empty())
+    }
+}
+
+impl FromStr for Placeholder {
+    type Err = TemplateError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim() {
+            "description" => Ok(Placeholder::Description),
+            "task" => Ok(Placeholder::Task),
+            "tags" => Ok(Placeholder::Tags),
+            "project" => Ok(Placeholder::Project),
+            "client" => Ok(Placeholder::Client),
+            _ => Err(TemplateError::UnknownPlaceholder(name.to_owned())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Segment {
+    Text(String),
+    /// Placeholder with fallbacks, replaced by the first available value.
+    Placeholder(Vec<Placeholder>),
+}
+
+/// Template for the description of time sheet entries, e.g.
+/// `{task}: {description}` or `{project} / {task|description}`.
+/// - `{description}` is replaced by the description of the time entry.
+/// - `{task}`, `{project}` and `{client}` are replaced by the task, project
+///   and client of the time entry or left empty.
+/// - `{tags}` is replaced by the comma separated tags of the time entry.
+/// - `{task|description}` is replaced by the first available value of the
+///   given placeholders.
+/// - `{{` and `}}` denote literal braces.
+///
+/// Brackets and separators of the template left empty by missing values are
+/// removed from the rendered description, e.g. `{task} ({tags})` renders as
+/// `Task` for a time entry without tags. Values are never changed.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(try_from = "String")]
+pub struct DescriptionTemplate {
+    template: String,
+    segments: Vec<Segment>,
+}
+
+impl DescriptionTemplate {
+    /// Render the description of the given time entry.
+    pub fn render(&self, entry: &Entry) -> String {
+        // Texts of the template and values of the placeholders, `None` for
+        // placeholders without value.
+        let mut parts = self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => Part::Text(text.clone()),
+                Segment::Placeholder(alternatives) => Part::Value(
+                    alternatives
+                        .iter()
+                        .find_map(|placeholder| placeholder.value(entry)),
+                ),
+            })
+            .collect::<Vec<_>>();
+        for index in 0..parts.len() {
+            if matches!(parts[index], Part::Value(None)) {
+                tidy(&mut parts, index);
+            }
+        }
+        parts
+            .into_iter()
+            .filter_map(|part| match part {
+                Part::Text(text) => Some(text),
+                Part::Value(value) => value,
+            })
+            .collect()
+    }
+}
+
+/// Rendered part of a description template.
+enum Part {
+    Text(String),
+    Value(Option<String>),
+}
+
+/// Remove the brackets or the separator around the empty placeholder at the
+/// given index from the adjacent texts of the template.
+/// - Brackets enclosing the placeholder are removed with the whitespace
+///   before the opening bracket.
+/// - A separator following a leading placeholder is removed, otherwise the
+///   separator preceding the placeholder.
+fn tidy(parts: &mut [Part], index: usize) {
+    const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('<', '>')];
+    let is_separator = |c: char| c.is_whitespace() || matches!(c, '-' | ',' | ':' | '|' | '/');
+    let text = |part: &Part| match part {
+        Part::Text(text) => Some(text.clone()),
+        Part::Value(_) => None,
+    };
+    let previous = index.checked_sub(1).and_then(|index| text(&parts[index]));
+    let next = parts.get(index + 1).and_then(text);
+    if let (Some(previous), Some(next)) = (&previous, &next) {
+        if let Some((open, close)) = BRACKETS
+            .iter()
+            .find(|(open, close)| previous.ends_with(*open) && next.starts_with(*close))
+        {
+            let previous = previous[..previous.len() - open.len_utf8()].trim_end();
+            parts[index - 1] = Part::Text(previous.to_owned());
+            parts[index + 1] = Part::Text(next[close.len_utf8()..].to_owned());
+            return;
+        }
+    }
+    let leading = parts[..index].iter().all(|part| match part {
+        Part::Text(text) => text.chars().all(is_separator),
+        Part::Value(value) => value.is_none(),
+    });
+    match (leading, previous, next) {
+        (true, _, Some(next)) => {
+            parts[index + 1] = Part::Text(next.trim_start_matches(is_separator).to_owned());
+        }
+        (false, Some(previous), _) => {
+            parts[index - 1] = Part::Text(previous.trim_end_matches(is_separator).to_owned());
+        }
+        _ => {}
+    }
+}
+
+impl FromStr for DescriptionTemplate {
+    type Err = TemplateError;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut segments = vec![];
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(TemplateError::Unclosed),
+                        }
+                    }
+                    let alternatives = name
+                        .split('|')
+                        .map(str::parse)
+                        .collect::<Result<Vec<Placeholder>, _>>()?;
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Placeholder(alternatives));
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(DescriptionTemplate {
+            template: template.to_owned(),
+            segments,
+        })
+    }
+}
+
+impl TryFrom<String> for DescriptionTemplate {
+    type Error = TemplateError;
+
+    fn try_from(template: String) -> Result<Self, Self::Error> {
+        template.parse()
+    }
+}
+
+impl fmt::Display for DescriptionTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    fn entry(task: Option<&str>, tags: &[&str]) -> Entry {
+        Entry {
+            description: "Fix login".to_string(),
+            project: Some("Project".to_string()),
+            client: None,
+            task: task.map(str::to_owned),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            billable: false,
+            start: Local.with_ymd_and_hms(2022, 10, 1, 8, 0, 0).unwrap(),
+            end: Local.with_ymd_and_hms(2022, 10, 1, 9, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_render_description_template() {
+        let template: DescriptionTemplate = "{task} - {description} ({tags})".parse().unwrap();
+        assert_eq!(
+            template.render(&entry(Some("Task 1"), &["meeting", "travel"])),
+            "Task 1 - Fix login (meeting, travel)"
+        );
+        assert_eq!(template.render(&entry(None, &[])), "Fix login");
+        assert_eq!(
+            "{{{tags}}}"
+                .parse::<DescriptionTemplate>()
+                .unwrap()
+                .render(&entry(None, &["on-call"])),
+            "{on-call}"
+        );
+    }
+
+    #[test]
+    fn test_render_description_template_with_fallbacks() {
+        let template: DescriptionTemplate =
+            "{client|project} / {task|description}".parse().unwrap();
+        assert_eq!(
+            template.render(&entry(Some("Task 1"), &[])),
+            "Project / Task 1"
+        );
+        assert_eq!(template.render(&entry(None, &[])), "Project / Fix login");
+        let template: DescriptionTemplate = "{task}: {description}".parse().unwrap();
+        assert_eq!(template.render(&entry(None, &[])), "Fix login");
+    }
+
+    #[test]
+    fn test_render_description_template_keeps_values() {
+        let entry = Entry {
+            description: "Refactor parse() - [WIP]:".to_string(),
+            ..entry(None, &[])
+        };
+        let template: DescriptionTemplate = "{description}".parse().unwrap();
+        assert_eq!(template.render(&entry), "Refactor parse() - [WIP]:");
+        let template: DescriptionTemplate = "{task}: {description} ({tags})".parse().unwrap();
+        assert_eq!(template.render(&entry), "Refactor parse() - [WIP]:");
+        let template: DescriptionTemplate = "{project} / {task} / {description}".parse().unwrap();
+        assert_eq!(
+            template.render(&entry),
+            "Project / Refactor parse() - [WIP]:"
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_description_template() {
+        assert_eq!(
+            "{task|user}".parse::<DescriptionTemplate>(),
+            Err(TemplateError::UnknownPlaceholder("user".to_string()))
+        );
+        assert_eq!(
+            "{task".parse::<DescriptionTemplate>(),
+            Err(TemplateError::Unclosed)
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::source::{Entry, Query, SourceError, TimeSource};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, TimeZone};
+use std::{fs, io, path::PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TextLogError {
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+    #[error("Invalid log line {line}: {value}")]
+    InvalidLine { line: usize, value: String },
+}
+
+/// Time source reading time entries from a plain-text log file with one entry
+/// per line like `2022-10-01 08:00-09:30 Description`. Empty lines and lines
+/// starting with `#` are ignored.
+#[derive(Clone, Debug)]
+pub struct TextLogSource {
+    pub path: PathBuf,
+}
+
+#[async_trait]
+impl TimeSource for TextLogSource {
+    async fn retrieve(&self, query: &Query) -> Result<Vec<Entry>, SourceError> {
+        let file = fs::File::open(&self.path).map_err(TextLogError::Io)?;
+        let mut entries = read_text_log(file)?;
+        entries.retain(|entry| query.matches(entry));
+        entries.sort_by_key(|entry| entry.start);
+        Ok(entries)
+    }
+}
+
+/// Read time entries from a plain-text log. An end time before the start time
+/// refers to the next day.
+pub fn read_text_log<R: io::Read>(mut rdr: R) -> Result<Vec<Entry>, TextLogError> {
+    let mut log = String::new();
+    rdr.read_to_string(&mut log)?;
+    log.lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|(index, line)| {
+            parse_line(line.trim()).ok_or_else(|| TextLogError::InvalidLine {
+                line: index + 1,
+                value: line.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Parse a single line of the log.
+fn parse_line(line: &str) -> Option<Entry> {
+    let (date, rest) = line.split_once(char::is_whitespace)?;
+    let (range, description) = rest
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .unwrap_or((rest.trim_start(), ""));
+    let (start, end) = range.split_once('-')?;
+
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    let start = NaiveTime::parse_from_str(start, "%H:%M").ok()?;
+    let end = NaiveTime::parse_from_str(end, "%H:%M").ok()?;
+    let end_date = if end < start {
+        date + Duration::days(1)
+    } else {
+        date
+    };
+    let local = |date: NaiveDate, time: NaiveTime| -> Option<DateTime<Local>> {
+        Local.from_local_datetime(&date.and_time(time)).earliest()
+    };
+
+    Some(Entry {
+        description: description.trim().to_owned(),
+        project: None,
+        client: None,
+        task: None,
+        tags: vec![],
+        billable: false,
+        start: local(date, start)?,
+        end: local(end_date, end)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_text_log() {
+        let log = "# October\n\
+                   2022-10-01 08:00-09:30 Fix login\n\
+                   \n\
+                   2022-10-01  23:00-01:15   Deployment  \n\
+                   2022-10-02 10:00-11:00\n";
+        let expected_result = vec![
+            Entry {
+                description: "Fix login".to_string(),
+                project: None,
+                client: None,
+                task: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 8, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 9, 30, 0).unwrap(),
+            },
+            Entry {
+                description: "Deployment".to_string(),
+                project: None,
+                client: None,
+                task: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 23, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 2, 1, 15, 0).unwrap(),
+            },
+            Entry {
+                description: String::new(),
+                project: None,
+                client: None,
+                task: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 2, 10, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 2, 11, 0, 0).unwrap(),
+            },
+        ];
+        let result = read_text_log(log.as_bytes()).unwrap();
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn test_read_text_log_invalid_line() {
+        let log = "2022-10-01 08:00-09:30 Fix login\n\
+                   01.10.2022 08:00-09:30 Fix login\n";
+        let result = read_text_log(log.as_bytes());
+        assert!(matches!(
+            result,
+            Err(TextLogError::InvalidLine { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,404 @@
+use crate::source::{Entry, Query, SourceError, TimeSource};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use serde::Deserialize;
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Format of the timestamps used by Timewarrior.
+const TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Debug, Error)]
+pub enum TimewarriorError {
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+    #[error("JSON deserialization error")]
+    Deserialization(#[from] serde_json::Error),
+    #[error("Invalid interval in line {line}: {value}")]
+    InvalidLine { line: usize, value: String },
+    #[error("Invalid time '{0}'")]
+    InvalidTime(String),
+}
+
+/// Time source reading time entries tracked locally with Timewarrior.
+/// The path may point to a data file (`YYYY-MM.data`), the data directory
+/// containing these files, or a file with the output of `timew export`. `-`
+/// reads the output of `timew export` or data file lines from standard input.
+///
+/// The first tag of an interval is used as the task. The annotation is used as
+/// description, falling back to the remaining tags. All tags are kept as tags
+/// of the entry.
+#[derive(Clone, Debug)]
+pub struct TimewarriorSource {
+    pub path: PathBuf,
+}
+
+#[async_trait]
+impl TimeSource for TimewarriorSource {
+    async fn retrieve(&self, query: &Query) -> Result<Vec<Entry>, SourceError> {
+        let mut entries = self
+            .read_intervals()?
+            .into_iter()
+            .filter_map(Interval::into_entry)
+            .collect::<Vec<_>>();
+        entries.retain(|entry| query.matches(entry));
+        entries.sort_by_key(|entry| entry.start);
+        Ok(entries)
+    }
+}
+
+impl TimewarriorSource {
+    /// Read all intervals from the data file, data directory, export file or
+    /// standard input.
+    pub fn read_intervals(&self) -> Result<Vec<Interval>, TimewarriorError> {
+        let intervals = if self.path == Path::new("-") {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            // Also accept data file lines, e.g. piped from `cat`.
+            if input.trim_start().starts_with('[') {
+                read_timewarrior_export(input.as_bytes())?
+            } else {
+                read_timewarrior_data(input.as_bytes())?
+            }
+        } else if self.path.is_dir() {
+            let mut intervals = vec![];
+            for dir_entry in fs::read_dir(&self.path)? {
+                let path = dir_entry?.path();
+                if is_monthly_data_file(&path) {
+                    intervals.extend(read_timewarrior_data(fs::File::open(path)?)?);
+                }
+            }
+            intervals
+        } else if self
+            .path
+            .extension()
+            .is_some_and(|extension| extension == "data")
+        {
+            read_timewarrior_data(fs::File::open(&self.path)?)?
+        } else {
+            read_timewarrior_export(fs::File::open(&self.path)?)?
+        };
+        Ok(intervals)
+    }
+}
+
+/// Tracked interval of Timewarrior.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Interval {
+    #[serde(deserialize_with = "deserialize_time")]
+    pub start: DateTime<Local>,
+    /// End of the interval, missing while tracking is active.
+    #[serde(default, deserialize_with = "deserialize_optional_time")]
+    pub end: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub annotation: Option<String>,
+}
+
+impl Interval {
+    /// Convert a closed interval into a normalized entry.
+    fn into_entry(self) -> Option<Entry> {
+        let task = self.tags.first().cloned();
+        let description = self
+            .annotation
+            .filter(|annotation| !annotation.is_empty())
+            .unwrap_or_else(|| {
+                self.tags
+                    .iter()
+                    .skip(1)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            });
+        Some(Entry {
+            description,
+            project: None,
+            client: None,
+            task,
+            tags: self.tags,
+            billable: false,
+            start: self.start,
+            end: self.end?,
+        })
+    }
+}
+
+/// Read intervals from the JSON output of `timew export`.
+pub fn read_timewarrior_export<R: io::Read>(rdr: R) -> Result<Vec<Interval>, TimewarriorError> {
+    Ok(serde_json::from_reader(rdr)?)
+}
+
+/// Read intervals from a Timewarrior data file with lines like
+/// `inc 20221001T080000Z - 20221001T090000Z # tag "other tag" # "annotation"`.
+pub fn read_timewarrior_data<R: io::Read>(mut rdr: R) -> Result<Vec<Interval>, TimewarriorError> {
+    let mut data = String::new();
+    rdr.read_to_string(&mut data)?;
+    data.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            parse_data_line(line).ok_or_else(|| TimewarriorError::InvalidLine {
+                line: index + 1,
+                value: line.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Whether the path is a monthly data file like `2022-10.data`, as opposed to
+/// other files of the data directory (e.g. `tags.data` or `undo.data`).
+fn is_monthly_data_file(path: &Path) -> bool {
+    let is_month = |stem: &str| {
+        let (year, month) = stem.split_once('-').unwrap_or_default();
+        year.len() == 4
+            && month.len() == 2
+            && year
+                .chars()
+                .chain(month.chars())
+                .all(|c| c.is_ascii_digit())
+    };
+    path.extension()
+        .is_some_and(|extension| extension == "data")
+        && path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(is_month)
+}
+
+/// Parse a single line of a Timewarrior data file.
+fn parse_data_line(line: &str) -> Option<Interval> {
+    let line = line.trim().strip_prefix("inc ")?;
+    let (range, rest) = match line.split_once(" # ") {
+        Some((range, rest)) => (range, Some(rest)),
+        None => (line.strip_suffix(" #").unwrap_or(line), None),
+    };
+    let (start, end) = match range.split_once(" - ") {
+        Some((start, end)) => (start, Some(end)),
+        None => (range, None),
+    };
+    let start = parse_time(start.trim()).ok()?;
+    let end = end.map(|end| parse_time(end.trim())).transpose().ok()?;
+
+    let mut words = split_words(rest.unwrap_or_default()).into_iter();
+    let mut tags = vec![];
+    let mut annotation = None;
+    while let Some((word, quoted)) = words.next() {
+        if word == "#" && !quoted {
+            annotation = Some(
+                words
+                    .by_ref()
+                    .map(|(word, _)| word)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+        } else {
+            tags.push(word);
+        }
+    }
+
+    Some(Interval {
+        start,
+        end,
+        tags,
+        annotation,
+    })
+}
+
+/// Split a string into whitespace separated words, keeping double quoted words
+/// together and unescaping them. Quoted words are marked as such.
+fn split_words(value: &str) -> Vec<(String, bool)> {
+    let mut words = vec![];
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c == '"' {
+            let mut word = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => word.extend(chars.next()),
+                    c => word.push(c),
+                }
+            }
+            words.push((word, true));
+        } else {
+            let mut word = String::from(c);
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                word.push(c);
+            }
+            words.push((word, false));
+        }
+    }
+    words
+}
+
+/// Parse a Timewarrior UTC timestamp like `20221001T080000Z`.
+fn parse_time(value: &str) -> Result<DateTime<Local>, TimewarriorError> {
+    NaiveDateTime::parse_from_str(value, TIME_FORMAT)
+        .map(|time| Utc.from_utc_datetime(&time).with_timezone(&Local))
+        .map_err(|_| TimewarriorError::InvalidTime(value.to_owned()))
+}
+
+fn deserialize_time<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<DateTime<Local>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_time(&value).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_optional_time<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Local>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_time(&value).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Local> {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_read_timewarrior_data() {
+        let data =
+            "inc 20221001T080000Z - 20221001T093000Z # Support \"customer x\" # \"Fix login\"\n\
+                    \n\
+                    inc 20221001T100000Z - 20221001T110000Z # Meeting\n\
+                    inc 20221001T120000Z - 20221001T130000Z\n\
+                    inc 20221001T140000Z # Development\n";
+        let expected_result = vec![
+            Interval {
+                start: utc("2022-10-01T08:00:00Z"),
+                end: Some(utc("2022-10-01T09:30:00Z")),
+                tags: vec!["Support".to_string(), "customer x".to_string()],
+                annotation: Some("Fix login".to_string()),
+            },
+            Interval {
+                start: utc("2022-10-01T10:00:00Z"),
+                end: Some(utc("2022-10-01T11:00:00Z")),
+                tags: vec!["Meeting".to_string()],
+                annotation: None,
+            },
+            Interval {
+                start: utc("2022-10-01T12:00:00Z"),
+                end: Some(utc("2022-10-01T13:00:00Z")),
+                tags: vec![],
+                annotation: None,
+            },
+            Interval {
+                start: utc("2022-10-01T14:00:00Z"),
+                end: None,
+                tags: vec!["Development".to_string()],
+                annotation: None,
+            },
+        ];
+        let result = read_timewarrior_data(data.as_bytes()).unwrap();
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn test_read_timewarrior_data_invalid_line() {
+        let data = "inc 20221001T080000Z - 20221001T093000Z\n\
+                    inc 2022-10-01 08:00 - 2022-10-01 09:00\n";
+        let result = read_timewarrior_data(data.as_bytes());
+        assert!(matches!(
+            result,
+            Err(TimewarriorError::InvalidLine { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_read_timewarrior_export() {
+        let json = r#"[
+            {"id":2,"start":"20221001T080000Z","end":"20221001T093000Z","tags":["Support","customer x"],"annotation":"Fix login"},
+            {"id":1,"start":"20221001T140000Z","tags":["Development"]}
+        ]"#;
+        let result = read_timewarrior_export(json.as_bytes()).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].end, Some(utc("2022-10-01T09:30:00Z")));
+        assert_eq!(result[0].annotation, Some("Fix login".to_string()));
+        assert_eq!(result[1].end, None);
+    }
+
+    #[test]
+    fn test_interval_into_entry() {
+        let interval = Interval {
+            start: utc("2022-10-01T08:00:00Z"),
+            end: Some(utc("2022-10-01T09:30:00Z")),
+            tags: vec![
+                "Support".to_string(),
+                "customer x".to_string(),
+                "urgent".to_string(),
+            ],
+            annotation: None,
+        };
+        let expected_result = Entry {
+            description: "customer x, urgent".to_string(),
+            project: None,
+            client: None,
+            task: Some("Support".to_string()),
+            tags: vec![
+                "Support".to_string(),
+                "customer x".to_string(),
+                "urgent".to_string(),
+            ],
+            billable: false,
+            start: utc("2022-10-01T08:00:00Z"),
+            end: utc("2022-10-01T09:30:00Z"),
+        };
+        assert_eq!(interval.clone().into_entry(), Some(expected_result));
+
+        let interval = Interval {
+            annotation: Some("Fix login".to_string()),
+            ..interval
+        };
+        assert_eq!(
+            interval.into_entry().unwrap().description,
+            "Fix login".to_string()
+        );
+
+        let interval = Interval {
+            start: utc("2022-10-01T08:00:00Z"),
+            end: None,
+            tags: vec![],
+            annotation: None,
+        };
+        assert_eq!(interval.into_entry(), None);
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_from_data_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("2022-09.data"),
+            "inc 20220930T080000Z - 20220930T090000Z # Support\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("2022-10.data"),
+            "inc 20221003T080000Z - 20221003T090000Z # Support\n\
+             inc 20221002T080000Z - 20221002T090000Z # Meeting\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("tags.data"), "{\"Support\":{\"count\":2}}").unwrap();
+        fs::write(dir.path().join("undo.data"), "txn:\n").unwrap();
+        let source = TimewarriorSource {
+            path: dir.path().to_owned(),
+        };
+        let query = Query::month(2022, 10).unwrap();
+        let result = source.retrieve(&query).await.unwrap();
+        let tasks: Vec<_> = result.iter().map(|entry| entry.task.as_deref()).collect();
+        assert_eq!(tasks, vec![Some("Meeting"), Some("Support")]);
+    }
+}
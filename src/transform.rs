@@ -1,61 +1,401 @@
-use crate::clockify::TimeEntry;
-use chrono::{DateTime, Duration, Local};
+use crate::{
+    rewrite::{rewrite_description, RewriteRule},
+    source::Entry,
+    template::DescriptionTemplate,
+};
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone};
+use serde::Deserialize;
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TransformError {
+    #[error("Unknown aggregation mode {0}")]
+    UnknownAggregation(String),
+    #[error("Unknown overlap strategy {0}")]
+    UnknownOverlapStrategy(String),
+    #[error("Overlapping time entries {0}")]
+    Overlap(Overlap),
+}
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TimeSheetEntry {
     pub description: String,
+    pub task: Option<String>,
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+    pub billable: bool,
     pub start: DateTime<Local>,
     pub end: DateTime<Local>,
     pub break_: Duration,
 }
 
-/// Transform Clockify time entries into time sheet entries.
+/// Options controlling the transformation of time entries, e.g. configured in
+/// the `[transform]` table of the config file.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TransformOptions {
+    /// Template for the description of time sheet entries. If not given, the
+    /// task or, if not available, the description of time entries is used.
+    pub description_template: Option<DescriptionTemplate>,
+    /// Ordered rules rewriting the descriptions of time sheet entries before
+    /// they are merged, so differently worded entries of the same activity
+    /// are merged as well.
+    pub rewrite: Vec<RewriteRule>,
+    /// How time sheet entries of equal description are combined.
+    pub aggregation: Aggregation,
+    /// Start of the rows of each day for the `per-task-per-day` aggregation.
+    /// If not given, rows start at the first time entry of the day.
+    pub day_start: Option<NaiveTime>,
+    /// Largest gap between subsequent entries of equal description which are
+    /// still merged, given in minutes in the config file. Entries with larger
+    /// gaps in between are kept separate.
+    #[serde(default, deserialize_with = "deserialize_minutes")]
+    pub max_merge_gap: Option<Duration>,
+    /// Gaps between merged entries shorter than this are counted as work time
+    /// instead of break, given in minutes in the config file.
+    #[serde(default, deserialize_with = "deserialize_minutes")]
+    pub absorb_gap: Option<Duration>,
+    /// Add breaks missing for the breaks required by the working time law to
+    /// the time sheet entries (see `compliance::insert_required_breaks`).
+    pub insert_breaks: bool,
+    /// How overlapping time entries are resolved before merging. If not
+    /// given, overlapping entries are kept and their time is counted twice.
+    pub overlap_strategy: Option<OverlapStrategy>,
+}
+
+fn deserialize_minutes<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    Ok(Option::<u32>::deserialize(deserializer)?.map(|minutes| Duration::minutes(minutes.into())))
+}
+
+/// Modes of combining time sheet entries of equal description.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Aggregation {
+    /// Merge subsequent entries of equal description, keeping the times of
+    /// the time entries.
+    #[default]
+    Merge,
+    /// Sum up the durations of each description per day and lay out one row
+    /// per description back-to-back. The daily totals are kept, while the
+    /// actual times of the time entries are not.
+    PerTaskPerDay,
+}
+
+impl Aggregation {
+    /// All aggregation modes.
+    pub const ALL: [Aggregation; 2] = [Aggregation::Merge, Aggregation::PerTaskPerDay];
+
+    /// Name of the aggregation mode as used in the config file and on the
+    /// command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aggregation::Merge => "merge",
+            Aggregation::PerTaskPerDay => "per-task-per-day",
+        }
+    }
+}
+
+impl FromStr for Aggregation {
+    type Err = TransformError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|aggregation| aggregation.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| TransformError::UnknownAggregation(s.to_owned()))
+    }
+}
+
+impl fmt::Display for Aggregation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Strategies of resolving overlapping time entries.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverlapStrategy {
+    /// Fail the transformation.
+    Error,
+    /// Start the later entry at the end of the earlier one. Later entries
+    /// within an earlier one are dropped.
+    TrimLater,
+    /// End the earlier entry at the start of the later one, e.g. for a timer
+    /// which was not stopped when starting the next one.
+    TrimEarlier,
+    /// Keep the longer entry and drop the shorter one.
+    KeepLongest,
+}
+
+impl OverlapStrategy {
+    /// All overlap strategies.
+    pub const ALL: [OverlapStrategy; 4] = [
+        OverlapStrategy::Error,
+        OverlapStrategy::TrimLater,
+        OverlapStrategy::TrimEarlier,
+        OverlapStrategy::KeepLongest,
+    ];
+
+    /// Name of the strategy as used in the config file and on the command
+    /// line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            OverlapStrategy::Error => "error",
+            OverlapStrategy::TrimLater => "trim-later",
+            OverlapStrategy::TrimEarlier => "trim-earlier",
+            OverlapStrategy::KeepLongest => "keep-longest",
+        }
+    }
+}
+
+impl FromStr for OverlapStrategy {
+    type Err = TransformError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|strategy| strategy.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| TransformError::UnknownOverlapStrategy(s.to_owned()))
+    }
+}
+
+impl fmt::Display for OverlapStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Overlap of two time entries, described by the description of the time
+/// sheet entries.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Overlap {
+    pub earlier: String,
+    pub later: String,
+    /// Start of the overlapping interval.
+    pub start: DateTime<Local>,
+    /// End of the overlapping interval.
+    pub end: DateTime<Local>,
+}
+
+impl fmt::Display for Overlap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}-{}: \"{}\" and \"{}\"",
+            self.start.format("%d.%m.%y"),
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            self.earlier,
+            self.later
+        )
+    }
+}
+
+/// Find the overlaps of the time entries, ordered by their start. The entries
+/// are described by their task or, if not available, their description.
+pub fn find_overlaps(time_entries: &[Entry]) -> Vec<Overlap> {
+    let mut time_entries = time_entries.iter().collect::<Vec<_>>();
+    time_entries.sort_by_key(|entry| entry.start);
+    let description = |entry: &Entry| entry.task.clone().unwrap_or(entry.description.clone());
+    let mut overlaps = vec![];
+    for (index, later) in time_entries.iter().enumerate() {
+        for earlier in &time_entries[..index] {
+            if earlier.end > later.start {
+                overlaps.push(Overlap {
+                    earlier: description(earlier),
+                    later: description(later),
+                    start: later.start,
+                    end: earlier.end.min(later.end),
+                });
+            }
+        }
+    }
+    overlaps
+}
+
+/// Transform time entries of a time source into time sheet entries.
 /// - Convert entries into into `TimeSheetEntry` by extracting the corresponding
 ///   information.
 /// - Merge subsequent entries with equal description by using in the `break_`
 ///   field accordingly.
-pub fn transform_time_entries(time_entries: Vec<TimeEntry>) -> Vec<TimeSheetEntry> {
-    merge_time_sheet_entries(convert_time_entries(time_entries))
+pub fn transform_time_entries(time_entries: Vec<Entry>) -> Vec<TimeSheetEntry> {
+    transform_time_entries_with_options(time_entries, &TransformOptions::default())
+        .expect("overlaps are kept without overlap strategy")
+}
+
+/// Transform time entries of a time source into time sheet entries like
+/// `transform_time_entries` using the given options. Descriptions are
+/// rewritten after the conversion and overlaps are resolved before merging or
+/// aggregating. Fails for overlapping time entries with the `error` overlap
+/// strategy.
+pub fn transform_time_entries_with_options(
+    time_entries: Vec<Entry>,
+    options: &TransformOptions,
+) -> Result<Vec<TimeSheetEntry>, TransformError> {
+    let time_sheet_entries = worked_time_sheet_entries(time_entries, options)?;
+    Ok(lay_out_time_sheet_entries(time_sheet_entries, options))
+}
+
+/// Transform time entries into time sheet entries at the times the work was
+/// done, i.e. like `transform_time_entries_with_options`, but without laying
+/// them out per task per day. Such entries are merged without gap thresholds
+/// instead, so the durations summed up per day stay the same. Checks of the
+/// working time law and inserted breaks rely on these times.
+pub fn worked_time_sheet_entries(
+    time_entries: Vec<Entry>,
+    options: &TransformOptions,
+) -> Result<Vec<TimeSheetEntry>, TransformError> {
+    let mut time_sheet_entries =
+        convert_time_entries(time_entries, options.description_template.as_ref());
+    if !options.rewrite.is_empty() {
+        for entry in &mut time_sheet_entries {
+            entry.description = rewrite_description(&options.rewrite, &entry.description);
+        }
+    }
+    if let Some(strategy) = options.overlap_strategy {
+        time_sheet_entries = resolve_overlaps(time_sheet_entries, strategy)?;
+    }
+    Ok(match options.aggregation {
+        Aggregation::Merge => merge_time_sheet_entries(
+            time_sheet_entries,
+            options.max_merge_gap,
+            options.absorb_gap,
+        ),
+        Aggregation::PerTaskPerDay => merge_time_sheet_entries(time_sheet_entries, None, None),
+    })
+}
+
+/// Lay out worked time sheet entries (see `worked_time_sheet_entries`) as
+/// rows of the time sheet according to the aggregation of the options.
+pub fn lay_out_time_sheet_entries(
+    time_sheet_entries: Vec<TimeSheetEntry>,
+    options: &TransformOptions,
+) -> Vec<TimeSheetEntry> {
+    match options.aggregation {
+        Aggregation::Merge => time_sheet_entries,
+        Aggregation::PerTaskPerDay => {
+            aggregate_time_sheet_entries_per_day(time_sheet_entries, options.day_start)
+        }
+    }
 }
 
-/// Convert Clockify time entries into `TimeSheetEntry` by extracting the
-/// corresponding information.
-/// Use `task.name` as the description for the time sheet entry, if available.
+/// Convert time entries into `TimeSheetEntry` by extracting the corresponding
+/// information, ordered by start time.
+/// Render the description with the given template, if any. Otherwise, use
+/// `task` as the description for the time sheet entry, if available.
 /// Fall back to using `description`, if no task is available.
-fn convert_time_entries(time_entries: Vec<TimeEntry>) -> Vec<TimeSheetEntry> {
+fn convert_time_entries(
+    mut time_entries: Vec<Entry>,
+    description_template: Option<&DescriptionTemplate>,
+) -> Vec<TimeSheetEntry> {
+    time_entries.sort_by_key(|entry| entry.start);
     time_entries
         .into_iter()
-        .rev() // reverse as Clockify starts newest entry first
         .map(|entry| TimeSheetEntry {
-            description: entry
-                .task
-                .map(|task| task.name)
-                .unwrap_or(entry.description),
-            start: entry.time_interval.start,
-            end: entry.time_interval.end,
+            description: match description_template {
+                Some(template) => template.render(&entry),
+                None => entry.task.clone().unwrap_or(entry.description),
+            },
+            task: entry.task,
+            project: entry.project,
+            tags: entry.tags,
+            billable: entry.billable,
+            start: entry.start,
+            end: entry.end,
             break_: Duration::zero(),
         })
         .collect()
 }
 
+/// Resolve overlapping time sheet entries, which are ordered by start, with
+/// the given strategy. Entries without duration left are dropped.
+fn resolve_overlaps(
+    time_entries: Vec<TimeSheetEntry>,
+    strategy: OverlapStrategy,
+) -> Result<Vec<TimeSheetEntry>, TransformError> {
+    let mut result: Vec<TimeSheetEntry> = Vec::with_capacity(time_entries.len());
+    for mut entry in time_entries {
+        match strategy {
+            OverlapStrategy::Error => {
+                if let Some(earlier) = result.iter().find(|earlier| earlier.end > entry.start) {
+                    return Err(TransformError::Overlap(Overlap {
+                        earlier: earlier.description.clone(),
+                        later: entry.description,
+                        start: entry.start,
+                        end: earlier.end.min(entry.end),
+                    }));
+                }
+            }
+            OverlapStrategy::TrimLater => {
+                if let Some(end) = result.iter().map(|earlier| earlier.end).max() {
+                    entry.start = entry.start.max(end);
+                }
+            }
+            OverlapStrategy::TrimEarlier => {
+                for earlier in &mut result {
+                    earlier.end = earlier.end.min(entry.start);
+                }
+                result.retain(|earlier| earlier.end > earlier.start);
+            }
+            OverlapStrategy::KeepLongest => {
+                let duration = entry.end - entry.start;
+                if result.iter().any(|earlier| {
+                    earlier.end > entry.start && earlier.end - earlier.start >= duration
+                }) {
+                    continue;
+                }
+                result.retain(|earlier| earlier.end <= entry.start);
+            }
+        }
+        if entry.end > entry.start {
+            result.push(entry);
+        }
+    }
+    Ok(result)
+}
+
 /// Merge subsequent time sheet entries with equal descriptions.
 /// - Time sheet entries are not merged across date boundaries.
+/// - Billable and non-billable time sheet entries are not merged.
+/// - Time sheet entries with a gap larger than `max_gap` in between are not
+///   merged, if given.
+/// - The tags of merged entries are combined.
 /// - With each merge, the `Duration` in the `break_` field is increased by the
 ///   time between the end of the first and the start of the second entry.
 ///   This way, the correct total of the list is kept.
+/// - Gaps shorter than `absorb_gap` are not added to the `break_` field but
+///   counted as work time, if given.
 /// - If descriptions alternate, entries are not merged as this would result
 ///   in time sheet entries overlapping each other. While the total of the list
 ///   would still be correct in this case due to the break times, this causes
 ///   the list to become hardly readable.
-fn merge_time_sheet_entries(time_entries: Vec<TimeSheetEntry>) -> Vec<TimeSheetEntry> {
+fn merge_time_sheet_entries(
+    time_entries: Vec<TimeSheetEntry>,
+    max_gap: Option<Duration>,
+    absorb_gap: Option<Duration>,
+) -> Vec<TimeSheetEntry> {
     let mut result: Vec<TimeSheetEntry> = Vec::with_capacity(time_entries.len());
     for entry in time_entries {
         if let Some(last) = result.last_mut() {
+            let gap = entry.start - last.end;
             if last.description == entry.description
+                && last.billable == entry.billable
                 && last.end.date_naive() == entry.end.date_naive()
+                && max_gap.is_none_or(|max_gap| gap <= max_gap)
             {
-                last.break_ = last.break_ + (entry.start - last.end);
+                if absorb_gap.is_none_or(|absorb_gap| gap >= absorb_gap) {
+                    last.break_ = last.break_ + gap;
+                }
                 last.end = entry.end;
+                for tag in entry.tags {
+                    if !last.tags.contains(&tag) {
+                        last.tags.push(tag);
+                    }
+                }
             } else {
                 result.push(entry);
             }
@@ -66,93 +406,219 @@ fn merge_time_sheet_entries(time_entries: Vec<TimeSheetEntry>) -> Vec<TimeSheetE
     result
 }
 
+/// Aggregate time sheet entries of equal description per day.
+/// - The durations of all entries with equal description and billable flag
+///   starting on the same day are summed up into a single entry.
+/// - The entries of each day are laid out back-to-back in the order of their
+///   first occurrence, starting at `day_start` or, if not given, at the start
+///   of the first entry of the day.
+/// - The tags of aggregated entries are combined.
+/// - Breaks of the entries are dropped, so the total of each day is kept.
+fn aggregate_time_sheet_entries_per_day(
+    time_entries: Vec<TimeSheetEntry>,
+    day_start: Option<NaiveTime>,
+) -> Vec<TimeSheetEntry> {
+    let mut days: Vec<Vec<TimeSheetEntry>> = vec![];
+    for entry in time_entries {
+        let duration = entry.end - entry.start - entry.break_;
+        match days.last_mut() {
+            Some(day) if day[0].start.date_naive() == entry.start.date_naive() => {
+                // The `end` of aggregated entries temporarily holds the start
+                // plus the summed up duration.
+                match day.iter_mut().find(|aggregated| {
+                    aggregated.description == entry.description
+                        && aggregated.billable == entry.billable
+                }) {
+                    Some(aggregated) => {
+                        aggregated.end += duration;
+                        for tag in entry.tags {
+                            if !aggregated.tags.contains(&tag) {
+                                aggregated.tags.push(tag);
+                            }
+                        }
+                    }
+                    None => day.push(TimeSheetEntry {
+                        end: entry.start + duration,
+                        break_: Duration::zero(),
+                        ..entry
+                    }),
+                }
+            }
+            _ => days.push(vec![TimeSheetEntry {
+                end: entry.start + duration,
+                break_: Duration::zero(),
+                ..entry
+            }]),
+        }
+    }
+
+    let mut result = Vec::with_capacity(days.iter().map(Vec::len).sum());
+    for day in days {
+        let first_start = day[0].start;
+        let mut start = day_start
+            .and_then(|day_start| {
+                Local
+                    .from_local_datetime(&first_start.date_naive().and_time(day_start))
+                    .earliest()
+            })
+            .unwrap_or(first_start);
+        for entry in day {
+            let end = start + (entry.end - entry.start);
+            result.push(TimeSheetEntry {
+                start,
+                end,
+                ..entry
+            });
+            start = end;
+        }
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::clockify::*;
-    use chrono::prelude::*;
 
     #[test]
-    fn test_convert_simple_entries_use_task_name_reverted() {
+    fn test_convert_simple_entries_use_task_name_sorted() {
         let time_entries = vec![
-            TimeEntry {
+            Entry {
                 description: "Entry 2".to_string(),
+                project: None,
+                client: None,
+                task: Some("Task 2".to_string()),
+                tags: vec![],
                 billable: true,
-                task_id: Some("ghijkl".to_string()),
-                time_interval: TimeInterval {
-                    start: Local.with_ymd_and_hms(2022, 10, 1, 14, 45, 0).unwrap(),
-                    end: Local.with_ymd_and_hms(2022, 10, 1, 15, 15, 15).unwrap(),
-                },
-                task: Some(Task {
-                    id: "ghijkl".to_string(),
-                    name: "Task 2".to_string(),
-                }),
+                start: Local.with_ymd_and_hms(2022, 10, 1, 14, 45, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 15, 15, 15).unwrap(),
             },
-            TimeEntry {
+            Entry {
                 description: "Entry 1".to_string(),
+                project: None,
+                client: None,
+                task: Some("Task 1".to_string()),
+                tags: vec![],
                 billable: true,
-                task_id: Some("abcdef".to_string()),
-                time_interval: TimeInterval {
-                    start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
-                    end: Local.with_ymd_and_hms(2022, 10, 1, 12, 25, 30).unwrap(),
-                },
-                task: Some(Task {
-                    id: "abcdef".to_string(),
-                    name: "Task 1".to_string(),
-                }),
+                start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 12, 25, 30).unwrap(),
             },
         ];
         let expected_result = vec![
             TimeSheetEntry {
                 description: "Task 1".to_string(),
-                start: time_entries[1].time_interval.start,
-                end: time_entries[1].time_interval.end,
+                task: Some("Task 1".to_string()),
+                project: None,
+                tags: vec![],
+                billable: true,
+                start: time_entries[1].start,
+                end: time_entries[1].end,
                 break_: Duration::zero(),
             },
             TimeSheetEntry {
                 description: "Task 2".to_string(),
-                start: time_entries[0].time_interval.start,
-                end: time_entries[0].time_interval.end,
+                task: Some("Task 2".to_string()),
+                project: None,
+                tags: vec![],
+                billable: true,
+                start: time_entries[0].start,
+                end: time_entries[0].end,
                 break_: Duration::zero(),
             },
         ];
-        let result = convert_time_entries(time_entries);
+        let result = convert_time_entries(time_entries, None);
         assert_eq!(result, expected_result);
     }
 
     #[test]
     fn test_convert_missing_task_uses_description() {
-        let time_entries = vec![TimeEntry {
+        let time_entries = vec![Entry {
             description: "Entry 1".to_string(),
-            billable: true,
-            task_id: None,
-            time_interval: TimeInterval {
-                start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
-                end: Local.with_ymd_and_hms(2022, 10, 1, 12, 25, 30).unwrap(),
-            },
+            project: None,
+            client: None,
             task: None,
+            tags: vec![],
+            billable: true,
+            start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
+            end: Local.with_ymd_and_hms(2022, 10, 1, 12, 25, 30).unwrap(),
         }];
         let expected_result = vec![TimeSheetEntry {
             description: "Entry 1".to_string(),
-            start: time_entries[0].time_interval.start,
-            end: time_entries[0].time_interval.end,
+            task: None,
+            project: None,
+            tags: vec![],
+            billable: true,
+            start: time_entries[0].start,
+            end: time_entries[0].end,
+            break_: Duration::zero(),
+        }];
+        let result = convert_time_entries(time_entries, None);
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn test_convert_uses_description_template() {
+        let time_entries = vec![Entry {
+            description: "Entry 1".to_string(),
+            project: None,
+            client: None,
+            task: Some("Task 1".to_string()),
+            tags: vec!["meeting".to_string()],
+            billable: true,
+            start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
+            end: Local.with_ymd_and_hms(2022, 10, 1, 12, 25, 30).unwrap(),
+        }];
+        let expected_result = vec![TimeSheetEntry {
+            description: "Task 1 (meeting)".to_string(),
+            task: Some("Task 1".to_string()),
+            project: None,
+            tags: vec!["meeting".to_string()],
+            billable: true,
+            start: time_entries[0].start,
+            end: time_entries[0].end,
             break_: Duration::zero(),
         }];
-        let result = convert_time_entries(time_entries);
+        let template = "{task} ({tags})".parse().unwrap();
+        let result = convert_time_entries(time_entries, Some(&template));
         assert_eq!(result, expected_result);
     }
 
+    #[test]
+    fn test_transform_options_from_toml() {
+        let options: TransformOptions = toml::from_str(
+            r#"
+            description_template = "{project} / {task|description}"
+            max_merge_gap = 60
+            "#,
+        )
+        .unwrap();
+        assert_eq!(options.max_merge_gap, Some(Duration::minutes(60)));
+        assert_eq!(options.absorb_gap, None);
+        assert_eq!(
+            options.description_template.unwrap().to_string(),
+            "{project} / {task|description}"
+        );
+        assert!(toml::from_str::<TransformOptions>(r#"description_template = "{user}""#).is_err());
+    }
+
     #[test]
     fn test_merge_subsequent_time_sheet_entries_of_same_task() {
         let time_sheet_entries = vec![
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 12, 25, 30).unwrap(),
                 break_: Duration::zero(),
             },
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 14, 45, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 15, 15, 15).unwrap(),
                 break_: Duration::zero(),
@@ -160,6 +626,10 @@ mod tests {
         ];
         let expected_result = vec![TimeSheetEntry {
             description: "Task 1".to_string(),
+            task: None,
+            project: None,
+            tags: vec![],
+            billable: false,
             // Start of first entry.
             start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
             // Start of last entry.
@@ -167,7 +637,7 @@ mod tests {
             // Break from 12:25:30 to 14:45:00 -> 2:19:30 = 8370 sec.
             break_: Duration::seconds(8370),
         }];
-        let result = merge_time_sheet_entries(time_sheet_entries);
+        let result = merge_time_sheet_entries(time_sheet_entries, None, None);
         assert_eq!(result, expected_result);
     }
 
@@ -176,25 +646,37 @@ mod tests {
         let time_sheet_entries = vec![
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 12, 25, 30).unwrap(),
                 break_: Duration::zero(),
             },
             TimeSheetEntry {
                 description: "Task 2".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 13, 0, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 13, 30, 0).unwrap(),
                 break_: Duration::zero(),
             },
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 14, 45, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 15, 15, 15).unwrap(),
                 break_: Duration::zero(),
             },
         ];
         let expected_result = time_sheet_entries.clone();
-        let result = merge_time_sheet_entries(time_sheet_entries);
+        let result = merge_time_sheet_entries(time_sheet_entries, None, None);
         assert_eq!(result, expected_result);
     }
 
@@ -203,19 +685,56 @@ mod tests {
         let time_sheet_entries = vec![
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 12, 25, 30).unwrap(),
                 break_: Duration::zero(),
             },
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 2, 14, 45, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 2, 15, 15, 15).unwrap(),
                 break_: Duration::zero(),
             },
         ];
         let expected_result = time_sheet_entries.clone();
-        let result = merge_time_sheet_entries(time_sheet_entries);
+        let result = merge_time_sheet_entries(time_sheet_entries, None, None);
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn test_merge_keep_time_sheet_entries_of_different_billable_flags() {
+        let time_sheet_entries = vec![
+            TimeSheetEntry {
+                description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: true,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 12, 25, 30).unwrap(),
+                break_: Duration::zero(),
+            },
+            TimeSheetEntry {
+                description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 14, 45, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 15, 15, 15).unwrap(),
+                break_: Duration::zero(),
+            },
+        ];
+        let expected_result = time_sheet_entries.clone();
+        let result = merge_time_sheet_entries(time_sheet_entries, None, None);
         assert_eq!(result, expected_result);
     }
 
@@ -224,18 +743,30 @@ mod tests {
         let time_sheet_entries = vec![
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 12, 25, 30).unwrap(),
                 break_: Duration::zero(),
             },
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 14, 45, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 15, 15, 15).unwrap(),
                 break_: Duration::zero(),
             },
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 16, 0, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 16, 15, 0).unwrap(),
                 break_: Duration::zero(),
@@ -243,6 +774,10 @@ mod tests {
         ];
         let expected_result = vec![TimeSheetEntry {
             description: "Task 1".to_string(),
+            task: None,
+            project: None,
+            tags: vec![],
+            billable: false,
             // Start of first entry.
             start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
             // Start of last entry.
@@ -251,108 +786,140 @@ mod tests {
             // Break from 15:15:15 to 16:00:00 -> 0:44:45 = 2685 sec.
             break_: Duration::seconds(8370 + 2685),
         }];
-        let result = merge_time_sheet_entries(time_sheet_entries);
+        let result = merge_time_sheet_entries(time_sheet_entries, None, None);
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn test_merge_respects_max_gap_and_absorbs_small_gaps() {
+        let entry = |start: (u32, u32, u32), end: (u32, u32, u32)| TimeSheetEntry {
+            description: "Task 1".to_string(),
+            task: None,
+            project: None,
+            tags: vec![],
+            billable: false,
+            start: Local
+                .with_ymd_and_hms(2022, 10, 1, start.0, start.1, start.2)
+                .unwrap(),
+            end: Local
+                .with_ymd_and_hms(2022, 10, 1, end.0, end.1, end.2)
+                .unwrap(),
+            break_: Duration::zero(),
+        };
+        let time_sheet_entries = vec![
+            entry((12, 10, 0), (12, 25, 30)),
+            entry((14, 45, 0), (15, 15, 15)),
+            entry((16, 0, 0), (16, 15, 0)),
+            entry((16, 20, 0), (16, 30, 0)),
+        ];
+        // The gap of 2:19:30 exceeds the maximum gap, the gap of 0:44:45 is
+        // kept as break and the gap of 0:05:00 is absorbed.
+        let expected_result = vec![
+            entry((12, 10, 0), (12, 25, 30)),
+            TimeSheetEntry {
+                break_: Duration::seconds(2685),
+                ..entry((14, 45, 0), (16, 30, 0))
+            },
+        ];
+        let result = merge_time_sheet_entries(
+            time_sheet_entries,
+            Some(Duration::minutes(60)),
+            Some(Duration::minutes(10)),
+        );
         assert_eq!(result, expected_result);
     }
 
     #[test]
     fn test_transform_complex_time_entries_example() {
         let time_entries = vec![
-            TimeEntry {
+            Entry {
                 description: "Entry 6".to_string(),
+                project: None,
+                client: None,
+                task: Some("Task 1".to_string()),
+                tags: vec![],
                 billable: true,
-                task_id: Some("abcdef".to_string()),
-                time_interval: TimeInterval {
-                    start: Local.with_ymd_and_hms(2022, 10, 1, 16, 0, 0).unwrap(),
-                    end: Local.with_ymd_and_hms(2022, 10, 1, 17, 0, 0).unwrap(),
-                },
-                task: Some(Task {
-                    id: "abcdef".to_string(),
-                    name: "Task 1".to_string(),
-                }),
+                start: Local.with_ymd_and_hms(2022, 10, 1, 16, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 17, 0, 0).unwrap(),
             },
-            TimeEntry {
+            Entry {
                 description: "Entry 5".to_string(),
-                billable: true,
-                task_id: None,
-                time_interval: TimeInterval {
-                    start: Local.with_ymd_and_hms(2022, 10, 1, 15, 50, 0).unwrap(),
-                    end: Local.with_ymd_and_hms(2022, 10, 1, 15, 55, 0).unwrap(),
-                },
+                project: None,
+                client: None,
                 task: None,
+                tags: vec![],
+                billable: true,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 15, 50, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 15, 55, 0).unwrap(),
             },
-            TimeEntry {
+            Entry {
                 description: "Entry 5".to_string(),
-                billable: true,
-                task_id: None,
-                time_interval: TimeInterval {
-                    start: Local.with_ymd_and_hms(2022, 10, 1, 15, 30, 0).unwrap(),
-                    end: Local.with_ymd_and_hms(2022, 10, 1, 15, 45, 0).unwrap(),
-                },
+                project: None,
+                client: None,
                 task: None,
+                tags: vec![],
+                billable: true,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 15, 30, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 15, 45, 0).unwrap(),
             },
-            TimeEntry {
+            Entry {
                 description: "Entry 4".to_string(),
+                project: None,
+                client: None,
+                task: Some("Task 2".to_string()),
+                tags: vec![],
                 billable: true,
-                task_id: Some("ghijkl".to_string()),
-                time_interval: TimeInterval {
-                    start: Local.with_ymd_and_hms(2022, 10, 1, 15, 5, 0).unwrap(),
-                    end: Local.with_ymd_and_hms(2022, 10, 1, 15, 10, 30).unwrap(),
-                },
-                task: Some(Task {
-                    id: "ghijkl".to_string(),
-                    name: "Task 2".to_string(),
-                }),
+                start: Local.with_ymd_and_hms(2022, 10, 1, 15, 5, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 15, 10, 30).unwrap(),
             },
-            TimeEntry {
+            Entry {
                 description: "Entry 3".to_string(),
+                project: None,
+                client: None,
+                task: Some("Task 1".to_string()),
+                tags: vec![],
                 billable: true,
-                task_id: Some("abcdef".to_string()),
-                time_interval: TimeInterval {
-                    start: Local.with_ymd_and_hms(2022, 10, 1, 14, 45, 0).unwrap(),
-                    end: Local.with_ymd_and_hms(2022, 10, 1, 15, 0, 15).unwrap(),
-                },
-                task: Some(Task {
-                    id: "abcdef".to_string(),
-                    name: "Task 1".to_string(),
-                }),
+                start: Local.with_ymd_and_hms(2022, 10, 1, 14, 45, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 15, 0, 15).unwrap(),
             },
-            TimeEntry {
+            Entry {
                 description: "Entry 2".to_string(),
+                project: None,
+                client: None,
+                task: Some("Task 1".to_string()),
+                tags: vec![],
                 billable: true,
-                task_id: Some("abcdef".to_string()),
-                time_interval: TimeInterval {
-                    start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
-                    end: Local.with_ymd_and_hms(2022, 10, 1, 12, 25, 30).unwrap(),
-                },
-                task: Some(Task {
-                    id: "abcdef".to_string(),
-                    name: "Task 1".to_string(),
-                }),
+                start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 12, 25, 30).unwrap(),
             },
-            TimeEntry {
+            Entry {
                 description: "Entry 1".to_string(),
+                project: None,
+                client: None,
+                task: Some("Task 1".to_string()),
+                tags: vec![],
                 billable: true,
-                task_id: Some("abcdef".to_string()),
-                time_interval: TimeInterval {
-                    start: Local.with_ymd_and_hms(2022, 9, 30, 12, 10, 0).unwrap(),
-                    end: Local.with_ymd_and_hms(2022, 9, 30, 12, 25, 30).unwrap(),
-                },
-                task: Some(Task {
-                    id: "abcdef".to_string(),
-                    name: "Task 1".to_string(),
-                }),
+                start: Local.with_ymd_and_hms(2022, 9, 30, 12, 10, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 9, 30, 12, 25, 30).unwrap(),
             },
         ];
         let expected_result = vec![
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: Some("Task 1".to_string()),
+                project: None,
+                tags: vec![],
+                billable: true,
                 start: Local.with_ymd_and_hms(2022, 9, 30, 12, 10, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 9, 30, 12, 25, 30).unwrap(),
                 break_: Duration::zero(),
             },
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: Some("Task 1".to_string()),
+                project: None,
+                tags: vec![],
+                billable: true,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 12, 10, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 15, 0, 15).unwrap(),
                 break_: Local.with_ymd_and_hms(2022, 10, 1, 14, 45, 0).unwrap()
@@ -360,12 +927,20 @@ mod tests {
             },
             TimeSheetEntry {
                 description: "Task 2".to_string(),
+                task: Some("Task 2".to_string()),
+                project: None,
+                tags: vec![],
+                billable: true,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 15, 5, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 15, 10, 30).unwrap(),
                 break_: Duration::zero(),
             },
             TimeSheetEntry {
                 description: "Entry 5".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: true,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 15, 30, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 15, 55, 0).unwrap(),
                 break_: Local.with_ymd_and_hms(2022, 10, 1, 15, 50, 0).unwrap()
@@ -373,6 +948,10 @@ mod tests {
             },
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: Some("Task 1".to_string()),
+                project: None,
+                tags: vec![],
+                billable: true,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 16, 0, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 17, 0, 0).unwrap(),
                 break_: Duration::zero(),
@@ -381,4 +960,189 @@ mod tests {
         let result = transform_time_entries(time_entries);
         assert_eq!(result, expected_result);
     }
+
+    #[test]
+    fn test_transform_merges_rewritten_descriptions() {
+        let time_entries = vec![
+            Entry {
+                description: "fix bug #123 again".to_string(),
+                project: None,
+                client: None,
+                task: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 8, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 9, 0, 0).unwrap(),
+            },
+            Entry {
+                description: "Fix bug #124".to_string(),
+                project: None,
+                client: None,
+                task: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 9, 15, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 10, 0, 0).unwrap(),
+            },
+        ];
+        let options = TransformOptions {
+            rewrite: vec![RewriteRule::regex(r"(?i)^fix bug #\d+.*$", "Bug fixing").unwrap()],
+            ..Default::default()
+        };
+        let expected_result = vec![TimeSheetEntry {
+            description: "Bug fixing".to_string(),
+            task: None,
+            project: None,
+            tags: vec![],
+            billable: false,
+            start: Local.with_ymd_and_hms(2022, 10, 1, 8, 0, 0).unwrap(),
+            end: Local.with_ymd_and_hms(2022, 10, 1, 10, 0, 0).unwrap(),
+            break_: Duration::minutes(15),
+        }];
+        let result = transform_time_entries_with_options(time_entries, &options).unwrap();
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn test_parse_aggregation() {
+        assert_eq!(
+            "per-task-per-day".parse::<Aggregation>().unwrap(),
+            Aggregation::PerTaskPerDay
+        );
+        assert_eq!(Aggregation::Merge.to_string(), "merge");
+        assert!(matches!(
+            "daily".parse::<Aggregation>(),
+            Err(TransformError::UnknownAggregation(name)) if name == "daily"
+        ));
+    }
+
+    #[test]
+    fn test_find_and_resolve_overlapping_time_entries() {
+        let time = |hour, min| Local.with_ymd_and_hms(2022, 10, 1, hour, min, 0).unwrap();
+        let entry = |description: &str, start, end| Entry {
+            description: description.to_string(),
+            project: None,
+            client: None,
+            task: None,
+            tags: vec![],
+            billable: true,
+            start,
+            end,
+        };
+        let time_entries = vec![
+            entry("Entry 2", time(10, 0), time(10, 30)),
+            entry("Entry 1", time(9, 0), time(11, 0)),
+            entry("Entry 3", time(10, 45), time(12, 0)),
+        ];
+        assert_eq!(
+            find_overlaps(&time_entries),
+            vec![
+                Overlap {
+                    earlier: "Entry 1".to_string(),
+                    later: "Entry 2".to_string(),
+                    start: time(10, 0),
+                    end: time(10, 30),
+                },
+                Overlap {
+                    earlier: "Entry 1".to_string(),
+                    later: "Entry 3".to_string(),
+                    start: time(10, 45),
+                    end: time(11, 0),
+                },
+            ]
+        );
+
+        let resolve = |strategy| {
+            let options = TransformOptions {
+                overlap_strategy: Some(strategy),
+                ..Default::default()
+            };
+            transform_time_entries_with_options(time_entries.clone(), &options).map(|result| {
+                result
+                    .into_iter()
+                    .map(|entry| (entry.description, entry.start, entry.end))
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert!(matches!(
+            resolve(OverlapStrategy::Error),
+            Err(TransformError::Overlap(overlap)) if overlap.later == "Entry 2"
+        ));
+        assert_eq!(
+            resolve(OverlapStrategy::TrimLater).unwrap(),
+            vec![
+                ("Entry 1".to_string(), time(9, 0), time(11, 0)),
+                ("Entry 3".to_string(), time(11, 0), time(12, 0)),
+            ]
+        );
+        assert_eq!(
+            resolve(OverlapStrategy::TrimEarlier).unwrap(),
+            vec![
+                ("Entry 1".to_string(), time(9, 0), time(10, 0)),
+                ("Entry 2".to_string(), time(10, 0), time(10, 30)),
+                ("Entry 3".to_string(), time(10, 45), time(12, 0)),
+            ]
+        );
+        assert_eq!(
+            resolve(OverlapStrategy::KeepLongest).unwrap(),
+            vec![("Entry 1".to_string(), time(9, 0), time(11, 0))]
+        );
+        assert_eq!(
+            "Trim-Later".parse::<OverlapStrategy>().unwrap(),
+            OverlapStrategy::TrimLater
+        );
+        assert!("shortest".parse::<OverlapStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_aggregate_alternating_time_sheet_entries_per_day() {
+        let entry =
+            |description: &str, billable: bool, start: (u32, u32, u32), end: (u32, u32, u32)| {
+                TimeSheetEntry {
+                    description: description.to_string(),
+                    task: None,
+                    project: None,
+                    tags: vec![description.to_lowercase()],
+                    billable,
+                    start: Local
+                        .with_ymd_and_hms(2022, 10, start.0, start.1, start.2, 0)
+                        .unwrap(),
+                    end: Local
+                        .with_ymd_and_hms(2022, 10, end.0, end.1, end.2, 0)
+                        .unwrap(),
+                    break_: Duration::zero(),
+                }
+            };
+        let time_sheet_entries = vec![
+            entry("Task 1", true, (1, 8, 0), (1, 9, 0)),
+            entry("Task 2", true, (1, 9, 0), (1, 9, 30)),
+            entry("Task 1", true, (1, 10, 0), (1, 10, 45)),
+            entry("Task 1", false, (1, 11, 0), (1, 11, 15)),
+            entry("Task 2", true, (1, 13, 0), (1, 14, 0)),
+            entry("Task 2", true, (2, 10, 0), (2, 11, 0)),
+        ];
+        let expected_result = vec![
+            entry("Task 1", true, (1, 8, 0), (1, 9, 45)),
+            entry("Task 2", true, (1, 9, 45), (1, 11, 15)),
+            entry("Task 1", false, (1, 11, 15), (1, 11, 30)),
+            entry("Task 2", true, (2, 10, 0), (2, 11, 0)),
+        ];
+        let result = aggregate_time_sheet_entries_per_day(time_sheet_entries.clone(), None);
+        assert_eq!(result, expected_result);
+
+        let day_start = NaiveTime::from_hms_opt(7, 30, 0);
+        let result = aggregate_time_sheet_entries_per_day(time_sheet_entries, day_start);
+        assert_eq!(
+            result[0].start,
+            Local.with_ymd_and_hms(2022, 10, 1, 7, 30, 0).unwrap()
+        );
+        assert_eq!(
+            result[2].end,
+            Local.with_ymd_and_hms(2022, 10, 1, 11, 0, 0).unwrap()
+        );
+        assert_eq!(
+            result[3].start,
+            Local.with_ymd_and_hms(2022, 10, 2, 7, 30, 0).unwrap()
+        );
+    }
 }
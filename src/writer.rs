@@ -1,32 +1,356 @@
-use crate::transform::TimeSheetEntry;
-use chrono::{DateTime, Duration, Local, Timelike};
-use std::io;
+use crate::{schedule::ScheduleReport, transform::TimeSheetEntry};
+use chrono::{DateTime, Duration, Local, NaiveDate, Timelike};
+use std::{error, fmt, io, path::Path, str::FromStr};
+use thiserror::Error;
+
+mod ics;
+
+pub use ics::write_ics;
+
+#[derive(Debug, Error)]
+pub enum WriterError {
+    #[error("CSV writer error")]
+    Csv(#[from] csv::Error),
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+    #[error("Unknown output format '{0}'")]
+    UnknownFormat(String),
+    #[error("Unknown column '{0}'")]
+    UnknownColumn(String),
+    #[error(transparent)]
+    Other(#[from] Box<dyn error::Error + Send + Sync>),
+}
+
+/// Output format for time sheet entries. Implement this trait and register it
+/// with a `WriterRegistry` to provide additional formats.
+pub trait TimeSheetWriter {
+    /// Name of the format, used for selecting the writer explicitly.
+    fn name(&self) -> &str;
+    /// File extensions (without leading dot) used for inferring the format
+    /// from an output file name. The first extension is used for default file
+    /// names.
+    fn extensions(&self) -> &[&str];
+    /// Write the given time sheet entries to the given writer.
+    fn write(
+        &self,
+        wtr: &mut dyn io::Write,
+        time_sheet_entries: &[TimeSheetEntry],
+    ) -> Result<(), WriterError>;
+}
+
+/// Optional column of the CSV time sheet, appended after the required columns.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CsvColumn {
+    /// `yes` for billable and `no` for non-billable entries.
+    Billable,
+    /// Comma separated tags of the entry.
+    Tags,
+    /// Target working time of the day according to the work schedule, given
+    /// in the first row of each day.
+    Target,
+    /// Working time of the day exceeding the target, given in the last row of
+    /// each day.
+    Overtime,
+    /// Flextime balance at the end of the day, given in the last row of each
+    /// day.
+    Balance,
+    /// Name of the public holiday or extra day off, given in the first row of
+    /// the day.
+    Holiday,
+}
+
+impl CsvColumn {
+    /// All optional columns.
+    pub const ALL: [CsvColumn; 6] = [
+        CsvColumn::Billable,
+        CsvColumn::Tags,
+        CsvColumn::Target,
+        CsvColumn::Overtime,
+        CsvColumn::Balance,
+        CsvColumn::Holiday,
+    ];
+
+    /// Name of the column used in the header.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CsvColumn::Billable => "billable",
+            CsvColumn::Tags => "tags",
+            CsvColumn::Target => "target",
+            CsvColumn::Overtime => "overtime",
+            CsvColumn::Balance => "balance",
+            CsvColumn::Holiday => "holiday",
+        }
+    }
+
+    /// Value of the column for the given entry, or for the marker row of an
+    /// absence without entry. Columns of the work schedule are left empty
+    /// without schedule report.
+    fn value(&self, entry: Option<&TimeSheetEntry>, row: &DayRow) -> String {
+        let schedule = row
+            .schedule
+            .map(|schedule| (schedule, schedule.day(row.date)));
+        match (self, schedule) {
+            (CsvColumn::Billable, _) => entry
+                .map(|entry| if entry.billable { "yes" } else { "no" }.to_owned())
+                .unwrap_or_default(),
+            (CsvColumn::Tags, _) => entry.map(|entry| entry.tags.join(", ")).unwrap_or_default(),
+            (CsvColumn::Target, Some((_, Some(day)))) if row.first => {
+                format_duration_field(&day.target)
+            }
+            (CsvColumn::Overtime, Some((_, Some(day)))) if row.last => {
+                format_duration_field(&day.overtime())
+            }
+            (CsvColumn::Balance, Some((schedule, _))) if row.last => {
+                format_duration_field(&schedule.balance_at(row.date))
+            }
+            (CsvColumn::Holiday, Some((_, Some(day)))) if row.first => {
+                day.holiday.clone().unwrap_or_default()
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+/// Position of a row within its day.
+struct DayRow<'a> {
+    date: NaiveDate,
+    first: bool,
+    last: bool,
+    schedule: Option<&'a ScheduleReport>,
+}
+
+impl FromStr for CsvColumn {
+    type Err = WriterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|column| column.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| WriterError::UnknownColumn(s.to_owned()))
+    }
+}
+
+impl fmt::Display for CsvColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Writer for the CSV format of the time sheet (see `write_csv`).
+#[derive(Clone, Debug, Default)]
+pub struct CsvWriter {
+    /// Optional columns to append.
+    pub columns: Vec<CsvColumn>,
+    /// Target and actual working time for the columns of the work schedule.
+    pub schedule: Option<ScheduleReport>,
+}
+
+impl TimeSheetWriter for CsvWriter {
+    fn name(&self) -> &str {
+        "csv"
+    }
+    fn extensions(&self) -> &[&str] {
+        &["csv"]
+    }
+    fn write(
+        &self,
+        wtr: &mut dyn io::Write,
+        time_sheet_entries: &[TimeSheetEntry],
+    ) -> Result<(), WriterError> {
+        Ok(write_csv_with_schedule(
+            wtr,
+            time_sheet_entries,
+            &self.columns,
+            self.schedule.as_ref(),
+        )?)
+    }
+}
+
+/// Writer for the iCalendar format (see `write_ics`).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IcsWriter;
+
+impl TimeSheetWriter for IcsWriter {
+    fn name(&self) -> &str {
+        "ics"
+    }
+    fn extensions(&self) -> &[&str] {
+        &["ics", "ical"]
+    }
+    fn write(
+        &self,
+        wtr: &mut dyn io::Write,
+        time_sheet_entries: &[TimeSheetEntry],
+    ) -> Result<(), WriterError> {
+        Ok(write_ics(wtr, time_sheet_entries)?)
+    }
+}
+
+/// Registry of the available output formats, keyed by format name.
+pub struct WriterRegistry {
+    writers: Vec<Box<dyn TimeSheetWriter>>,
+}
+
+impl WriterRegistry {
+    /// Create a registry containing the built-in formats (CSV and iCalendar).
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry.register(CsvWriter::default());
+        registry.register(IcsWriter);
+        registry
+    }
+
+    /// Create a registry without any formats.
+    pub fn empty() -> Self {
+        Self { writers: vec![] }
+    }
+
+    /// Register a writer. A previously registered writer with the same name is
+    /// replaced in place, so the order of the formats is kept.
+    pub fn register<W: TimeSheetWriter + 'static>(&mut self, writer: W) {
+        match self
+            .writers
+            .iter_mut()
+            .find(|existing| existing.name() == writer.name())
+        {
+            Some(existing) => *existing = Box::new(writer),
+            None => self.writers.push(Box::new(writer)),
+        }
+    }
+
+    /// Names of all registered formats in order of registration.
+    pub fn names(&self) -> Vec<&str> {
+        self.writers.iter().map(|writer| writer.name()).collect()
+    }
+
+    /// Look up the writer for the given format name.
+    pub fn get(&self, name: &str) -> Option<&dyn TimeSheetWriter> {
+        self.writers
+            .iter()
+            .find(|writer| writer.name().eq_ignore_ascii_case(name))
+            .map(|writer| writer.as_ref())
+    }
+
+    /// Look up the writer for the extension of the given file name.
+    pub fn for_path(&self, path: &Path) -> Option<&dyn TimeSheetWriter> {
+        let extension = path.extension()?.to_str()?;
+        self.writers
+            .iter()
+            .find(|writer| {
+                writer
+                    .extensions()
+                    .iter()
+                    .any(|candidate| candidate.eq_ignore_ascii_case(extension))
+            })
+            .map(|writer| writer.as_ref())
+    }
+
+    /// Select a writer by the explicitly given format name, falling back to the
+    /// extension of the given file name and finally to the first registered
+    /// format.
+    pub fn select(
+        &self,
+        format: Option<&str>,
+        path: Option<&Path>,
+    ) -> Result<&dyn TimeSheetWriter, WriterError> {
+        if let Some(format) = format {
+            return self
+                .get(format)
+                .ok_or_else(|| WriterError::UnknownFormat(format.to_owned()));
+        }
+        path.and_then(|path| self.for_path(path))
+            .or_else(|| self.writers.first().map(|writer| writer.as_ref()))
+            .ok_or_else(|| WriterError::UnknownFormat(String::new()))
+    }
+}
+
+impl Default for WriterRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /// Write given time sheet entries as CSV to the given writer. The fields are
 /// formatted as required by the time sheet and time values are rounded to the
 /// nearest minute and the date is only written for the first entry of a day.
 pub fn write_csv<W: io::Write>(
     wtr: W,
-    time_sheet_entries: &Vec<TimeSheetEntry>,
+    time_sheet_entries: &[TimeSheetEntry],
+) -> Result<(), csv::Error> {
+    write_csv_with_columns(wtr, time_sheet_entries, &[])
+}
+
+/// Write given time sheet entries as CSV like `write_csv` with the given
+/// optional columns appended.
+pub fn write_csv_with_columns<W: io::Write>(
+    wtr: W,
+    time_sheet_entries: &[TimeSheetEntry],
+    columns: &[CsvColumn],
+) -> Result<(), csv::Error> {
+    write_csv_with_schedule(wtr, time_sheet_entries, columns, None)
+}
+
+/// Write given time sheet entries as CSV like `write_csv_with_columns`, taking
+/// the values of the columns of the work schedule from the given report.
+/// Holidays and days of absence in the report get a marker row without times,
+/// described by the name of the holiday or the label of the absence, before
+/// the entries of the day, so they are listed even without entries.
+pub fn write_csv_with_schedule<W: io::Write>(
+    wtr: W,
+    time_sheet_entries: &[TimeSheetEntry],
+    columns: &[CsvColumn],
+    schedule: Option<&ScheduleReport>,
 ) -> Result<(), csv::Error> {
     let mut wtr = csv::Writer::from_writer(wtr);
-    wtr.write_record(["date", "start", "end", "break", "description"])?;
-    let mut last_date: Option<String> = None;
-    for entry in time_sheet_entries {
-        let date = entry.start.format("%d.%m.%y").to_string();
-        let date = if last_date.as_ref() == Some(&date) {
-            String::new()
+    let mut header = vec!["date", "start", "end", "break", "description"];
+    header.extend(columns.iter().map(CsvColumn::name));
+    wtr.write_record(header)?;
+    let mut rows = time_sheet_entries
+        .iter()
+        .map(|entry| (entry.start.date_naive(), Ok(entry)))
+        .chain(
+            schedule
+                .iter()
+                .flat_map(|schedule| &schedule.days)
+                .filter_map(|day| {
+                    let label = day.absence.as_deref().or(day.holiday.as_deref())?;
+                    Some((day.date, Err(label)))
+                }),
+        )
+        .collect::<Vec<(NaiveDate, Result<&TimeSheetEntry, &str>)>>();
+    // The stable sort keeps the order of the entries within each day.
+    rows.sort_by_key(|(date, content)| (*date, content.is_ok()));
+    for (index, (date, content)) in rows.iter().enumerate() {
+        let row = DayRow {
+            date: *date,
+            first: index == 0 || rows[index - 1].0 != *date,
+            last: rows.get(index + 1).is_none_or(|next| next.0 != *date),
+            schedule,
+        };
+        let date = if row.first {
+            row.date.format("%d.%m.%y").to_string()
         } else {
-            last_date = Some(date.clone());
-            date
+            String::new()
+        };
+        let mut record = match content {
+            Ok(entry) => vec![
+                date,
+                format_time_field(&entry.start),
+                format_time_field(&entry.end),
+                format_break_field(&entry.break_),
+                entry.description.clone(),
+            ],
+            Err(label) => vec![
+                date,
+                String::new(),
+                String::new(),
+                String::new(),
+                label.to_string(),
+            ],
         };
-        wtr.write_record([
-            &date,
-            &format_time_field(&entry.start),
-            &format_time_field(&entry.end),
-            &format_break_field(&entry.break_),
-            &entry.description,
-        ])?;
+        let entry = content.as_ref().ok().copied();
+        record.extend(columns.iter().map(|column| column.value(entry, &row)));
+        wtr.write_record(record)?;
     }
     wtr.flush()?;
     Ok(())
@@ -34,6 +358,7 @@ pub fn write_csv<W: io::Write>(
 
 /// Format a time field (start/end) to hh:mm format while rounding up to the
 /// next minute, if the second is >=30. (12:30:29 -> 12:30, 12:30:30 -> 12:31)
+/// Rounding up beyond the end of the day results in `24:00`.
 fn format_time_field(time: &DateTime<Local>) -> String {
     let mut hour = time.hour();
     let mut minute = time.minute();
@@ -47,6 +372,15 @@ fn format_time_field(time: &DateTime<Local>) -> String {
     format!("{hour:02}:{minute:02}")
 }
 
+/// Format a signed duration of the work schedule to h:mm format, rounded to
+/// the nearest minute.
+fn format_duration_field(duration: &Duration) -> String {
+    let minutes = (duration.num_seconds() as f64 / 60.0).round() as i64;
+    let sign = if minutes < 0 { "-" } else { "" };
+    let minutes = minutes.abs();
+    format!("{sign}{}:{:02}", minutes / 60, minutes % 60)
+}
+
 /// Format the break field to h:mm format while rounding up to the next minute,
 /// if the second is >=30. (01:30:29 -> 1:30, 01:30:30 -> 1:31)
 /// Leave the field empty, if no break is recorded for the entry.
@@ -69,6 +403,11 @@ fn format_break_field(duration: &Duration) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        absence::{Absence, AbsenceCredit},
+        holidays::Holidays,
+        schedule::{daily_working_time, WorkSchedule},
+    };
     use chrono::prelude::*;
 
     #[test]
@@ -87,6 +426,8 @@ mod tests {
         assert_eq!(format_time_field(&time), "09:06");
         let time = Local.with_ymd_and_hms(2022, 10, 1, 8, 59, 30).unwrap();
         assert_eq!(format_time_field(&time), "09:00");
+        let time = Local.with_ymd_and_hms(2022, 10, 1, 23, 59, 30).unwrap();
+        assert_eq!(format_time_field(&time), "24:00");
     }
 
     #[test]
@@ -120,18 +461,30 @@ mod tests {
         let entries = vec![
             TimeSheetEntry {
                 description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 8, 0, 29).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 8, 59, 30).unwrap(),
                 break_: Duration::zero(),
             },
             TimeSheetEntry {
                 description: "Task 2".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 1, 13, 0, 31).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 1, 14, 59, 30).unwrap(),
                 break_: Duration::seconds(3630),
             },
             TimeSheetEntry {
                 description: "Task 3".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
                 start: Local.with_ymd_and_hms(2022, 10, 2, 8, 0, 0).unwrap(),
                 end: Local.with_ymd_and_hms(2022, 10, 2, 9, 0, 0).unwrap(),
                 break_: Duration::zero(),
@@ -148,4 +501,252 @@ mod tests {
 "#
         );
     }
+
+    #[test]
+    fn test_writer_with_columns() {
+        let entries = vec![
+            TimeSheetEntry {
+                description: "Task 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec!["meeting".to_string(), "travel".to_string()],
+                billable: true,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 8, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 9, 0, 0).unwrap(),
+                break_: Duration::zero(),
+            },
+            TimeSheetEntry {
+                description: "Task 2".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
+                start: Local.with_ymd_and_hms(2022, 10, 1, 9, 0, 0).unwrap(),
+                end: Local.with_ymd_and_hms(2022, 10, 1, 10, 0, 0).unwrap(),
+                break_: Duration::zero(),
+            },
+        ];
+        let writer = CsvWriter {
+            columns: vec!["Billable".parse().unwrap(), "tags".parse().unwrap()],
+            schedule: None,
+        };
+        let mut buffer: Vec<u8> = Vec::new();
+        writer.write(&mut buffer, &entries).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            r#"date,start,end,break,description,billable,tags
+01.10.22,08:00,09:00,,Task 1,yes,"meeting, travel"
+,09:00,10:00,,Task 2,no,
+"#
+        );
+        assert!(matches!(
+            "project".parse::<CsvColumn>(),
+            Err(WriterError::UnknownColumn(name)) if name == "project"
+        ));
+    }
+
+    #[test]
+    fn test_writer_with_schedule_columns() {
+        let entry = |day: u32, start: u32, end: u32| TimeSheetEntry {
+            description: "Task 1".to_string(),
+            task: None,
+            project: None,
+            tags: vec![],
+            billable: false,
+            start: Local.with_ymd_and_hms(2022, 10, day, start, 0, 0).unwrap(),
+            end: Local.with_ymd_and_hms(2022, 10, day, end, 0, 0).unwrap(),
+            break_: Duration::zero(),
+        };
+        let entries = vec![entry(3, 8, 12), entry(3, 13, 18), entry(5, 8, 14)];
+        let schedule = WorkSchedule {
+            weekly_hours: Some(40.0),
+            ..Default::default()
+        };
+        let working_time = daily_working_time(
+            entries
+                .iter()
+                .map(|entry| (entry.start, entry.end - entry.start)),
+        );
+        let report = ScheduleReport::new(
+            &schedule,
+            NaiveDate::from_ymd_opt(2022, 10, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2022, 10, 6).unwrap(),
+            &working_time,
+            Duration::minutes(30),
+        );
+        let writer = CsvWriter {
+            columns: vec![CsvColumn::Target, CsvColumn::Overtime, CsvColumn::Balance],
+            schedule: Some(report),
+        };
+        let mut buffer: Vec<u8> = Vec::new();
+        writer.write(&mut buffer, &entries).unwrap();
+        // Tuesday without time entries reduces the balance by 8 hours.
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            "date,start,end,break,description,target,overtime,balance
+03.10.22,08:00,12:00,,Task 1,8:00,,
+,13:00,18:00,,Task 1,,1:00,1:30
+05.10.22,08:00,14:00,,Task 1,8:00,-2:00,-8:30
+"
+        );
+    }
+
+    #[test]
+    fn test_writer_marks_holidays() {
+        let entries = vec![TimeSheetEntry {
+            description: "Task 1".to_string(),
+            task: None,
+            project: None,
+            tags: vec![],
+            billable: false,
+            start: Local.with_ymd_and_hms(2022, 10, 3, 8, 0, 0).unwrap(),
+            end: Local.with_ymd_and_hms(2022, 10, 3, 10, 0, 0).unwrap(),
+            break_: Duration::zero(),
+        }];
+        let schedule = WorkSchedule {
+            weekly_hours: Some(40.0),
+            holidays: Holidays {
+                region: Some("DE".parse().unwrap()),
+                extra: [(
+                    NaiveDate::from_ymd_opt(2022, 10, 4).unwrap(),
+                    "Betriebsausflug".to_string(),
+                )]
+                .into(),
+            },
+            ..Default::default()
+        };
+        let report = ScheduleReport::new(
+            &schedule,
+            NaiveDate::from_ymd_opt(2022, 10, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2022, 10, 5).unwrap(),
+            &daily_working_time([(entries[0].start, Duration::hours(2))]),
+            Duration::zero(),
+        );
+        let writer = CsvWriter {
+            columns: vec![CsvColumn::Target, CsvColumn::Overtime, CsvColumn::Holiday],
+            schedule: Some(report),
+        };
+        let mut buffer: Vec<u8> = Vec::new();
+        writer.write(&mut buffer, &entries).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            "date,start,end,break,description,target,overtime,holiday
+03.10.22,,,,Tag der Deutschen Einheit,0:00,,Tag der Deutschen Einheit
+,08:00,10:00,,Task 1,,2:00,
+04.10.22,,,,Betriebsausflug,0:00,0:00,Betriebsausflug
+"
+        );
+    }
+
+    #[test]
+    fn test_writer_with_absence_rows() {
+        let entries = vec![TimeSheetEntry {
+            description: "Task 1".to_string(),
+            task: None,
+            project: None,
+            tags: vec![],
+            billable: true,
+            start: Local.with_ymd_and_hms(2022, 10, 4, 13, 0, 0).unwrap(),
+            end: Local.with_ymd_and_hms(2022, 10, 4, 17, 0, 0).unwrap(),
+            break_: Duration::zero(),
+        }];
+        let schedule = WorkSchedule {
+            weekly_hours: Some(40.0),
+            ..Default::default()
+        };
+        let absence = |day, label: &str, half_day| Absence {
+            date: NaiveDate::from_ymd_opt(2022, 10, day).unwrap(),
+            label: label.to_string(),
+            credit: AbsenceCredit::Target,
+            half_day,
+        };
+        let report = ScheduleReport::new(
+            &schedule,
+            NaiveDate::from_ymd_opt(2022, 10, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2022, 10, 5).unwrap(),
+            &daily_working_time([(entries[0].start, Duration::hours(4))]),
+            Duration::zero(),
+        )
+        .with_absences(&[absence(3, "Krank", false), absence(4, "Urlaub", true)]);
+        let writer = CsvWriter {
+            columns: vec![CsvColumn::Billable, CsvColumn::Target, CsvColumn::Balance],
+            schedule: Some(report),
+        };
+        let mut buffer: Vec<u8> = Vec::new();
+        writer.write(&mut buffer, &entries).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            "date,start,end,break,description,billable,target,balance
+03.10.22,,,,Krank,,0:00,0:00
+04.10.22,,,,Urlaub,,4:00,
+,13:00,17:00,,Task 1,yes,,0:00
+"
+        );
+    }
+
+    #[test]
+    fn test_registry_get_by_name() {
+        let registry = WriterRegistry::new();
+        assert_eq!(registry.names(), vec!["csv", "ics"]);
+        assert_eq!(registry.get("csv").unwrap().name(), "csv");
+        assert_eq!(registry.get("ICS").unwrap().name(), "ics");
+        assert!(registry.get("pdf").is_none());
+    }
+
+    #[test]
+    fn test_registry_for_path() {
+        let registry = WriterRegistry::new();
+        let writer = registry.for_path(Path::new("2022-10.csv")).unwrap();
+        assert_eq!(writer.name(), "csv");
+        let writer = registry.for_path(Path::new("out/2022-10.ICAL")).unwrap();
+        assert_eq!(writer.name(), "ics");
+        assert!(registry.for_path(Path::new("2022-10.pdf")).is_none());
+        assert!(registry.for_path(Path::new("2022-10")).is_none());
+    }
+
+    #[test]
+    fn test_registry_select() {
+        let registry = WriterRegistry::new();
+        let path = Path::new("2022-10.ics");
+        let writer = registry.select(Some("csv"), Some(path)).unwrap();
+        assert_eq!(writer.name(), "csv");
+        let writer = registry.select(None, Some(path)).unwrap();
+        assert_eq!(writer.name(), "ics");
+        let writer = registry.select(None, Some(Path::new("2022-10.txt")));
+        assert_eq!(writer.unwrap().name(), "csv");
+        let result = registry.select(Some("pdf"), Some(path));
+        assert!(matches!(result, Err(WriterError::UnknownFormat(name)) if name == "pdf"));
+        let empty_registry = WriterRegistry::empty();
+        let result = empty_registry.select(None, None);
+        assert!(matches!(result, Err(WriterError::UnknownFormat(_))));
+    }
+
+    #[test]
+    fn test_registry_register_custom_writer() {
+        struct CountWriter;
+        impl TimeSheetWriter for CountWriter {
+            fn name(&self) -> &str {
+                "csv"
+            }
+            fn extensions(&self) -> &[&str] {
+                &["txt"]
+            }
+            fn write(
+                &self,
+                wtr: &mut dyn io::Write,
+                time_sheet_entries: &[TimeSheetEntry],
+            ) -> Result<(), WriterError> {
+                Ok(write!(wtr, "{}", time_sheet_entries.len())?)
+            }
+        }
+
+        let mut registry = WriterRegistry::new();
+        registry.register(CountWriter);
+        assert_eq!(registry.names(), vec!["csv", "ics"]);
+        assert_eq!(registry.select(None, None).unwrap().extensions(), ["txt"]);
+        let writer = registry.for_path(Path::new("2022-10.txt")).unwrap();
+        let mut buffer: Vec<u8> = Vec::new();
+        writer.write(&mut buffer, &[]).unwrap();
+        assert_eq!(buffer, b"0");
+    }
 }
@@ -0,0 +1,533 @@
+use super::format_break_field;
+use crate::transform::TimeSheetEntry;
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDateTime, Offset, TimeZone, Utc,
+};
+use std::io;
+
+static PRODUCT_ID: &str = "-//clockify-to-time-sheet//EN";
+static UID_DOMAIN: &str = "clockify-to-time-sheet";
+
+/// Maximum length of a content line in octets (excluding the line break).
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Write given time sheet entries as iCalendar (RFC 5545) to the given writer.
+/// Each entry becomes a `VEVENT` with the description as summary and the task
+/// and project as categories. Start and end are written with the IANA name of
+/// the local time zone as `TZID`, which is defined by a `VTIMEZONE` component,
+/// if it can be determined, and in UTC otherwise.
+pub fn write_ics<W: io::Write>(wtr: W, time_sheet_entries: &[TimeSheetEntry]) -> io::Result<()> {
+    let time_zone = iana_time_zone::get_timezone()
+        .ok()
+        .map(|name| VTimeZone::new(name, &Local, time_sheet_entries));
+    write_ics_with(wtr, time_sheet_entries, time_zone.as_ref(), Utc::now())
+}
+
+/// Time zone of the start and end of the entries, written as `VTIMEZONE`
+/// component for the `TZID` parameters referring to it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct VTimeZone {
+    /// IANA name like `Europe/Berlin`.
+    name: String,
+    observances: Vec<Observance>,
+}
+
+/// Period of a time zone with a constant UTC offset, written as `STANDARD` or
+/// `DAYLIGHT` component.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Observance {
+    daylight: bool,
+    /// Onset in local time before the transition.
+    start: NaiveDateTime,
+    offset_from: FixedOffset,
+    offset_to: FixedOffset,
+}
+
+impl VTimeZone {
+    /// Time zone with the observances of the given time zone from the start of
+    /// the first until the end of the last entry.
+    fn new<Tz: TimeZone>(
+        name: String,
+        time_zone: &Tz,
+        time_sheet_entries: &[TimeSheetEntry],
+    ) -> Self {
+        let start = time_sheet_entries.iter().map(|entry| entry.start).min();
+        let end = time_sheet_entries.iter().map(|entry| entry.end).max();
+        let observances = match (start, end) {
+            (Some(start), Some(end)) => observances(
+                time_zone,
+                start.with_timezone(&Utc),
+                end.with_timezone(&Utc),
+            ),
+            _ => vec![],
+        };
+        VTimeZone { name, observances }
+    }
+}
+
+/// Observances of the time zone between the given times: one starting at
+/// `start` and one for each transition until `end`, which are found in steps
+/// of an hour and narrowed down to the second.
+fn observances<Tz: TimeZone>(
+    time_zone: &Tz,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<Observance> {
+    let offset = |time: DateTime<Utc>| time_zone.offset_from_utc_datetime(&time.naive_utc()).fix();
+    // The standard time of the year is the smaller offset of January and July,
+    // which also holds for the southern hemisphere.
+    let daylight = |time: DateTime<Utc>| {
+        let standard = [1, 7]
+            .into_iter()
+            .filter_map(|month| {
+                Utc.with_ymd_and_hms(time.year(), month, 1, 0, 0, 0)
+                    .single()
+            })
+            .map(|time| offset(time).local_minus_utc())
+            .min()
+            .unwrap_or_default();
+        offset(time).local_minus_utc() > standard
+    };
+    let local =
+        |time: DateTime<Utc>, offset: FixedOffset| time.with_timezone(&offset).naive_local();
+
+    let mut result = vec![Observance {
+        daylight: daylight(start),
+        start: local(start, offset(start)),
+        offset_from: offset(start),
+        offset_to: offset(start),
+    }];
+    let mut time = start;
+    while time < end {
+        let next = (time + Duration::hours(1)).min(end);
+        if offset(next) != offset(time) {
+            let (mut before, mut after) = (time, next);
+            while after - before > Duration::seconds(1) {
+                let middle = before + (after - before) / 2;
+                if offset(middle) == offset(before) {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            result.push(Observance {
+                daylight: daylight(after),
+                start: local(after, offset(before)),
+                offset_from: offset(before),
+                offset_to: offset(after),
+            });
+        }
+        time = next;
+    }
+    result
+}
+
+/// Write given time sheet entries as iCalendar using the given time zone for
+/// `TZID` parameters and the given time stamp for `DTSTAMP` properties.
+fn write_ics_with<W: io::Write>(
+    mut wtr: W,
+    time_sheet_entries: &[TimeSheetEntry],
+    time_zone: Option<&VTimeZone>,
+    dtstamp: DateTime<Utc>,
+) -> io::Result<()> {
+    write_line(&mut wtr, "BEGIN:VCALENDAR")?;
+    write_line(&mut wtr, "VERSION:2.0")?;
+    write_line(&mut wtr, &format!("PRODID:{PRODUCT_ID}"))?;
+    write_line(&mut wtr, "CALSCALE:GREGORIAN")?;
+    if let Some(time_zone) = time_zone.filter(|time_zone| !time_zone.observances.is_empty()) {
+        write_line(&mut wtr, "BEGIN:VTIMEZONE")?;
+        write_line(&mut wtr, &format!("TZID:{}", time_zone.name))?;
+        for observance in &time_zone.observances {
+            let component = if observance.daylight {
+                "DAYLIGHT"
+            } else {
+                "STANDARD"
+            };
+            write_line(&mut wtr, &format!("BEGIN:{component}"))?;
+            write_line(
+                &mut wtr,
+                &format!("DTSTART:{}", observance.start.format("%Y%m%dT%H%M%S")),
+            )?;
+            write_line(
+                &mut wtr,
+                &format!("TZOFFSETFROM:{}", format_offset(&observance.offset_from)),
+            )?;
+            write_line(
+                &mut wtr,
+                &format!("TZOFFSETTO:{}", format_offset(&observance.offset_to)),
+            )?;
+            write_line(&mut wtr, &format!("END:{component}"))?;
+        }
+        write_line(&mut wtr, "END:VTIMEZONE")?;
+    }
+    let time_zone = time_zone.map(|time_zone| time_zone.name.as_str());
+    for entry in time_sheet_entries {
+        write_line(&mut wtr, "BEGIN:VEVENT")?;
+        write_line(&mut wtr, &format!("UID:{}", uid(entry)))?;
+        write_line(&mut wtr, &format!("DTSTAMP:{}", format_utc_field(&dtstamp)))?;
+        write_line(
+            &mut wtr,
+            &format!("DTSTART{}", format_date_time_field(&entry.start, time_zone)),
+        )?;
+        write_line(
+            &mut wtr,
+            &format!("DTEND{}", format_date_time_field(&entry.end, time_zone)),
+        )?;
+        write_line(
+            &mut wtr,
+            &format!("SUMMARY:{}", escape_text(&entry.description)),
+        )?;
+        let categories = [&entry.task, &entry.project]
+            .into_iter()
+            .flatten()
+            .map(|category| escape_text(category))
+            .collect::<Vec<_>>();
+        if !categories.is_empty() {
+            write_line(&mut wtr, &format!("CATEGORIES:{}", categories.join(",")))?;
+        }
+        let break_ = format_break_field(&entry.break_);
+        if !break_.is_empty() {
+            write_line(
+                &mut wtr,
+                &format!("DESCRIPTION:{}", escape_text(&format!("Break: {break_}"))),
+            )?;
+        }
+        write_line(&mut wtr, "END:VEVENT")?;
+    }
+    write_line(&mut wtr, "END:VCALENDAR")?;
+    wtr.flush()
+}
+
+/// Format the value of a date-time property including the separating colon,
+/// either as local time with `TZID` parameter or as UTC time, if no time zone
+/// name is given.
+fn format_date_time_field(time: &DateTime<Local>, time_zone: Option<&str>) -> String {
+    match time_zone {
+        Some(time_zone) => format!(";TZID={time_zone}:{}", time.format("%Y%m%dT%H%M%S")),
+        None => format!(":{}", format_utc_field(&time.with_timezone(&Utc))),
+    }
+}
+
+/// Format a UTC offset like `+0200`.
+fn format_offset(offset: &FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.abs() / 60;
+    format!("{sign}{:02}{:02}", minutes / 60, minutes % 60)
+}
+
+/// Unique identifier of the event of an entry, derived from its start and
+/// description, so it is kept when the entries are exported again and
+/// calendar applications update the events instead of duplicating them.
+fn uid(entry: &TimeSheetEntry) -> String {
+    // 64-bit FNV-1a hash, which is stable across platforms and releases.
+    let hash = entry
+        .description
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!(
+        "{}-{hash:016x}@{UID_DOMAIN}",
+        format_utc_field(&entry.start.with_timezone(&Utc))
+    )
+}
+
+/// Format a time in UTC as iCalendar date-time value.
+fn format_utc_field(time: &DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape special characters in a text value.
+fn escape_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '\n' => result.push_str("\\n"),
+            '\r' => {}
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Write a content line terminated by CRLF, folding it into multiple lines if
+/// it exceeds the maximum line length. Lines are never split within a UTF-8
+/// character.
+fn write_line<W: io::Write>(wtr: &mut W, line: &str) -> io::Result<()> {
+    let mut remaining = line;
+    let mut max_length = MAX_LINE_LENGTH;
+    while remaining.len() > max_length {
+        let mut split = max_length;
+        while !remaining.is_char_boundary(split) {
+            split -= 1;
+        }
+        wtr.write_all(&remaining.as_bytes()[..split])?;
+        wtr.write_all(b"\r\n ")?;
+        remaining = &remaining[split..];
+        // Continuation lines start with a space, which counts towards the
+        // line length.
+        max_length = MAX_LINE_LENGTH - 1;
+    }
+    wtr.write_all(remaining.as_bytes())?;
+    wtr.write_all(b"\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{prelude::*, Duration};
+
+    #[test]
+    fn test_escape_text() {
+        assert_eq!(escape_text("Task 1"), "Task 1");
+        assert_eq!(
+            escape_text("Review; fix, test\\deploy\nagain"),
+            "Review\\; fix\\, test\\\\deploy\\nagain"
+        );
+    }
+
+    #[test]
+    fn test_write_line_folding() {
+        let mut buffer: Vec<u8> = Vec::new();
+        write_line(&mut buffer, &"a".repeat(75)).unwrap();
+        assert_eq!(buffer, format!("{}\r\n", "a".repeat(75)).into_bytes());
+
+        let mut buffer: Vec<u8> = Vec::new();
+        write_line(&mut buffer, &"a".repeat(160)).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            format!(
+                "{}\r\n {}\r\n {}\r\n",
+                "a".repeat(75),
+                "a".repeat(74),
+                "a".repeat(11)
+            )
+        );
+    }
+
+    #[test]
+    fn test_write_line_folding_keeps_characters_intact() {
+        let mut buffer: Vec<u8> = Vec::new();
+        // "ä" is encoded in two octets, so the 38th character would be split.
+        write_line(&mut buffer, &"ä".repeat(40)).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            format!("{}\r\n {}\r\n", "ä".repeat(37), "ä".repeat(3))
+        );
+    }
+
+    #[test]
+    fn test_writer() {
+        let entries = vec![
+            TimeSheetEntry {
+                description: "Task 1".to_string(),
+                task: Some("Task 1".to_string()),
+                project: Some("Project A".to_string()),
+                tags: vec![],
+                billable: false,
+                start: Utc
+                    .with_ymd_and_hms(2022, 10, 1, 6, 0, 0)
+                    .unwrap()
+                    .with_timezone(&Local),
+                end: Utc
+                    .with_ymd_and_hms(2022, 10, 1, 7, 0, 0)
+                    .unwrap()
+                    .with_timezone(&Local),
+                break_: Duration::zero(),
+            },
+            TimeSheetEntry {
+                description: "Entry 2, part 1".to_string(),
+                task: None,
+                project: None,
+                tags: vec![],
+                billable: false,
+                start: Utc
+                    .with_ymd_and_hms(2022, 10, 1, 11, 0, 0)
+                    .unwrap()
+                    .with_timezone(&Local),
+                end: Utc
+                    .with_ymd_and_hms(2022, 10, 1, 13, 0, 0)
+                    .unwrap()
+                    .with_timezone(&Local),
+                break_: Duration::seconds(3600),
+            },
+        ];
+        let dtstamp = Utc.with_ymd_and_hms(2022, 11, 2, 10, 0, 0).unwrap();
+        let uid_1 = uid(&entries[0]);
+        let uid_2 = uid(&entries[1]);
+        let mut buffer: Vec<u8> = Vec::new();
+        write_ics_with(&mut buffer, &entries, None, dtstamp).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            format!(
+                "BEGIN:VCALENDAR\r\n\
+                 VERSION:2.0\r\n\
+                 PRODID:-//clockify-to-time-sheet//EN\r\n\
+                 CALSCALE:GREGORIAN\r\n\
+                 BEGIN:VEVENT\r\n\
+                 UID:{uid_1}\r\n\
+                 DTSTAMP:20221102T100000Z\r\n\
+                 DTSTART:20221001T060000Z\r\n\
+                 DTEND:20221001T070000Z\r\n\
+                 SUMMARY:Task 1\r\n\
+                 CATEGORIES:Task 1,Project A\r\n\
+                 END:VEVENT\r\n\
+                 BEGIN:VEVENT\r\n\
+                 UID:{uid_2}\r\n\
+                 DTSTAMP:20221102T100000Z\r\n\
+                 DTSTART:20221001T110000Z\r\n\
+                 DTEND:20221001T130000Z\r\n\
+                 SUMMARY:Entry 2\\, part 1\r\n\
+                 DESCRIPTION:Break: 1:00\r\n\
+                 END:VEVENT\r\n\
+                 END:VCALENDAR\r\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_writer_with_time_zone() {
+        let start = Utc
+            .with_ymd_and_hms(2022, 10, 1, 6, 0, 0)
+            .unwrap()
+            .with_timezone(&Local);
+        let end = start + Duration::hours(1);
+        let entries = vec![TimeSheetEntry {
+            description: "Task 1".to_string(),
+            task: None,
+            project: None,
+            tags: vec![],
+            billable: false,
+            start,
+            end,
+            break_: Duration::zero(),
+        }];
+        let time_zone = VTimeZone {
+            name: "Europe/Berlin".to_string(),
+            observances: vec![Observance {
+                daylight: true,
+                start: NaiveDate::from_ymd_opt(2022, 10, 1)
+                    .unwrap()
+                    .and_hms_opt(8, 0, 0)
+                    .unwrap(),
+                offset_from: FixedOffset::east_opt(7200).unwrap(),
+                offset_to: FixedOffset::east_opt(7200).unwrap(),
+            }],
+        };
+        let dtstamp = Utc.with_ymd_and_hms(2022, 11, 2, 10, 0, 0).unwrap();
+        let uid = uid(&entries[0]);
+        let mut buffer: Vec<u8> = Vec::new();
+        write_ics_with(&mut buffer, &entries, Some(&time_zone), dtstamp).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            format!(
+                "BEGIN:VCALENDAR\r\n\
+                 VERSION:2.0\r\n\
+                 PRODID:-//clockify-to-time-sheet//EN\r\n\
+                 CALSCALE:GREGORIAN\r\n\
+                 BEGIN:VTIMEZONE\r\n\
+                 TZID:Europe/Berlin\r\n\
+                 BEGIN:DAYLIGHT\r\n\
+                 DTSTART:20221001T080000\r\n\
+                 TZOFFSETFROM:+0200\r\n\
+                 TZOFFSETTO:+0200\r\n\
+                 END:DAYLIGHT\r\n\
+                 END:VTIMEZONE\r\n\
+                 BEGIN:VEVENT\r\n\
+                 UID:{uid}\r\n\
+                 DTSTAMP:20221102T100000Z\r\n\
+                 DTSTART;TZID=Europe/Berlin:{}\r\n\
+                 DTEND;TZID=Europe/Berlin:{}\r\n\
+                 SUMMARY:Task 1\r\n\
+                 END:VEVENT\r\n\
+                 END:VCALENDAR\r\n",
+                start.format("%Y%m%dT%H%M%S"),
+                end.format("%Y%m%dT%H%M%S")
+            )
+        );
+    }
+
+    #[test]
+    fn test_observances() {
+        let time_zone = chrono_tz::Europe::Berlin;
+        let start = Utc.with_ymd_and_hms(2022, 10, 28, 6, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2022, 11, 2, 6, 0, 0).unwrap();
+        let local = |hour| {
+            NaiveDate::from_ymd_opt(2022, 10, 30)
+                .unwrap()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap()
+        };
+        let summer = FixedOffset::east_opt(7200).unwrap();
+        let winter = FixedOffset::east_opt(3600).unwrap();
+        assert_eq!(
+            observances(&time_zone, start, end),
+            vec![
+                Observance {
+                    daylight: true,
+                    start: NaiveDate::from_ymd_opt(2022, 10, 28)
+                        .unwrap()
+                        .and_hms_opt(8, 0, 0)
+                        .unwrap(),
+                    offset_from: summer,
+                    offset_to: summer,
+                },
+                Observance {
+                    daylight: false,
+                    start: local(3),
+                    offset_from: summer,
+                    offset_to: winter,
+                },
+            ]
+        );
+        let start = Utc.with_ymd_and_hms(2022, 11, 2, 6, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2022, 11, 3, 6, 0, 0).unwrap();
+        assert_eq!(observances(&time_zone, start, end).len(), 1);
+    }
+
+    #[test]
+    fn test_format_offset() {
+        assert_eq!(
+            format_offset(&FixedOffset::east_opt(7200).unwrap()),
+            "+0200"
+        );
+        assert_eq!(
+            format_offset(&FixedOffset::west_opt(12600).unwrap()),
+            "-0330"
+        );
+    }
+
+    #[test]
+    fn test_uid_is_derived_from_start_and_description() {
+        let entry = TimeSheetEntry {
+            description: "Task 1".to_string(),
+            task: None,
+            project: None,
+            tags: vec![],
+            billable: false,
+            start: Utc
+                .with_ymd_and_hms(2022, 10, 1, 6, 0, 0)
+                .unwrap()
+                .with_timezone(&Local),
+            end: Utc
+                .with_ymd_and_hms(2022, 10, 1, 7, 0, 0)
+                .unwrap()
+                .with_timezone(&Local),
+            break_: Duration::zero(),
+        };
+        assert_eq!(
+            uid(&entry),
+            "20221001T060000Z-f23d2879dbd5426f@clockify-to-time-sheet"
+        );
+        let renamed = TimeSheetEntry {
+            description: "Task 2".to_string(),
+            ..entry.clone()
+        };
+        assert_ne!(uid(&renamed), uid(&entry));
+    }
+}